@@ -0,0 +1,59 @@
+//! Measures full and incremental builds of a wiki with thousands of pages
+//!
+//! Run with `cargo bench --bench manifest`, the page count can be set via `WIKI_BENCH_PAGES`.
+
+extern crate wikilib;
+
+use wikilib::Wiki;
+use wikilib::filehash::{Manifest, MANIFEST_FILE};
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+fn main() {
+    let pages = env::var("WIKI_BENCH_PAGES").ok()
+        .and_then(|pages| pages.parse().ok())
+        .unwrap_or(5000);
+
+    let bench_dir = env::temp_dir().join("wiki_bench_manifest");
+    let input_dir = bench_dir.join("input");
+    let output_dir = bench_dir.join("output");
+    if bench_dir.exists() {
+        fs::remove_dir_all(&bench_dir).expect("Unable to clean the bench directory");
+    }
+
+    // Generate the pages, spread over some sections
+    for page in 0..pages {
+        let section = input_dir.join(format!("section{}", page % 50));
+        fs::create_dir_all(&section).expect("Unable to create section");
+        let mut file = File::create(section.join(format!("page{}.md", page)))
+            .expect("Unable to create page");
+        write!(file, "# Page {}\n\nSome *content* with a [link](page{}.md).\n", page, page + 1)
+            .expect("Unable to write page");
+    }
+
+    let input = input_dir.to_str().expect("Unable to stringify input directory");
+    let output = output_dir.to_str().expect("Unable to stringify output directory");
+    let mut wiki = Wiki::new();
+    wiki.read_from_directory(input).expect("Unable to read input directory");
+
+    let start = Instant::now();
+    wiki.read_content_from_current_paths(input, output).expect("Full build failed");
+    println!("full build of {} pages:        {:?}", pages, start.elapsed());
+
+    let start = Instant::now();
+    wiki.read_content_from_current_paths(input, output).expect("Incremental build failed");
+    println!("incremental build of {} pages: {:?}", pages, start.elapsed());
+
+    let manifest_file = output_dir.join(MANIFEST_FILE);
+    let start = Instant::now();
+    let manifest = Manifest::load(manifest_file.to_str().expect("Unable to stringify manifest"));
+    let found = manifest.pages.iter().filter(|page| manifest.page(&page.source).is_some()).count();
+    println!("manifest load and {} lookups:  {:?}", found, start.elapsed());
+    assert_eq!(found, pages);
+
+    fs::remove_dir_all(Path::new(&bench_dir)).expect("Unable to clean the bench directory");
+}
@@ -0,0 +1,81 @@
+//! Everything related to the breadcrumb navigation
+//!
+//! Every page shows the path from the site root down to itself, one entry per directory. A
+//! directory with an `index` page is titled and linked by that page, other directories show
+//! their name.
+
+use encoding::url_path;
+use renderer::escape_html;
+use template::relative_root;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The titles of all sections, which are directories with an index page
+pub struct Breadcrumbs {
+    /// The title of the root entry
+    site_title: String,
+    /// The title of each section by its directory relative to the output directory
+    sections: BTreeMap<PathBuf, String>,
+}
+
+impl Breadcrumbs {
+    /// Creates breadcrumbs starting at the root titled `site_title`
+    pub fn new(site_title: &str) -> Self {
+        Breadcrumbs {
+            site_title: site_title.to_owned(),
+            sections: BTreeMap::new(),
+        }
+    }
+
+    /// Titles the section at `directory` relative to the output directory
+    pub fn add_section(&mut self, directory: &Path, title: &str) {
+        self.sections.insert(directory.to_path_buf(), title.to_owned());
+    }
+
+    /// Everything which influences the breadcrumbs of the pages
+    pub fn fingerprint(&self) -> String {
+        self.sections.iter()
+            .map(|(directory, title)| format!("{}\0{}", url_path(directory), title))
+            .collect::<Vec<_>>()
+            .join("\0")
+    }
+
+    /// Renders the breadcrumbs of the page titled `page_title` at `output`, which is the path of
+    /// the page relative to the output directory. The root index page has none.
+    pub fn render(&self, output: &Path, page_title: &str) -> String {
+        let mut directory = output.parent().unwrap_or_else(|| Path::new(""));
+        let is_index = output.file_stem().is_some_and(|stem| stem == "index");
+        if is_index && directory.as_os_str().is_empty() {
+            return String::new();
+        }
+        if is_index {
+            // Index pages represent their directory
+            directory = directory.parent().unwrap_or_else(|| Path::new(""));
+        }
+
+        let root = relative_root(output);
+        let mut html = String::from("<nav class=\"breadcrumbs\">\n<ol>\n");
+        html.push_str(&format!("<li><a href=\"{}index.html\">{}</a></li>\n",
+                               root, escape_html(&self.site_title)));
+        let mut current = PathBuf::new();
+        for component in directory.components() {
+            current.push(component);
+            match self.sections.get(&current) {
+                Some(title) => {
+                    html.push_str(&format!("<li><a href=\"{}{}/index.html\">{}</a></li>\n",
+                                           root, url_path(&current), escape_html(title)));
+                }
+                None => {
+                    html.push_str(&format!("<li><span>{}</span></li>\n",
+                                           escape_html(&component.as_os_str()
+                                                                 .to_string_lossy())));
+                }
+            }
+        }
+        html.push_str(&format!("<li aria-current=\"page\">{}</li>\n</ol>\n</nav>",
+                               escape_html(page_title)));
+        html
+    }
+}
@@ -0,0 +1,505 @@
+//! Everything related to the wiki configuration
+//!
+//! The configuration is assembled with the precedence CLI > environment variables > config file
+//! > defaults. This module covers everything except the CLI, which is applied by the caller.
+
+use encoding::serde_path;
+use error::*;
+use server::Binding;
+use template::is_builtin_theme;
+use toml;
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// The name of the configuration file searched in the input directory
+pub const CONFIG_FILE: &str = "wiki.toml";
+
+/// The environment variables holding paths, which need not be valid UTF-8
+const PATH_VARIABLES: &[&str] = &["WIKI_INPUT_DIRECTORY", "WIKI_OUTPUT_DIRECTORY",
+                                  "WIKI_FILE_DIRECTORY", "WIKI_SERVER_UNIX_SOCKET"];
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// The configuration of a wiki
+pub struct Config {
+    /// The directory containing the markdown files
+    #[serde(with = "serde_path")]
+    pub input_directory: PathBuf,
+    /// The directory where the HTML output is generated
+    #[serde(with = "serde_path")]
+    pub output_directory: PathBuf,
+    /// The directory within the output directory where attached files are stored
+    #[serde(with = "serde_path")]
+    pub file_directory: PathBuf,
+    /// The title of the site, shown on every page
+    pub title: String,
+    /// The URL the generated site is published at, used as prefix for absolute links
+    pub base_url: String,
+    /// The name of a built-in theme or a theme directory containing a `page.html` layout, a
+    /// `style.css` and a `static` directory, all optional
+    pub theme: Option<String>,
+    /// The theme variables: colors, fonts and logo
+    pub style: StyleConfig,
+    /// Markdown related settings
+    pub markdown: MarkdownConfig,
+    /// Plain text related settings
+    pub text: TextConfig,
+    /// Settings of rendering diagram code blocks
+    pub diagrams: DiagramsConfig,
+    /// Settings of finding the markdown files
+    pub discovery: DiscoveryConfig,
+    /// Additional input directories, mounted at URL prefixes
+    pub mounts: Vec<MountConfig>,
+    /// The entries of the site menu, besides the pages listed by their front matter
+    pub menu: Vec<MenuConfig>,
+    /// Settings of the sitemap and robots.txt
+    pub sitemap: SitemapConfig,
+    /// Settings of the feeds of recently changed pages
+    pub feeds: FeedsConfig,
+    /// Settings of the recent changes page
+    pub recent: RecentConfig,
+    /// Settings of the integrated HTTP server
+    pub server: ServerConfig,
+    /// Settings of plugins, keyed by the plugin name
+    pub plugins: BTreeMap<String, toml::Value>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            input_directory: PathBuf::from("."),
+            output_directory: PathBuf::from("output"),
+            file_directory: PathBuf::from("files"),
+            title: "Wiki".to_owned(),
+            base_url: String::new(),
+            theme: None,
+            style: StyleConfig::default(),
+            markdown: MarkdownConfig::default(),
+            text: TextConfig::default(),
+            diagrams: DiagramsConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            mounts: Vec::new(),
+            menu: Vec::new(),
+            sitemap: SitemapConfig::default(),
+            feeds: FeedsConfig::default(),
+            recent: RecentConfig::default(),
+            server: ServerConfig::default(),
+            plugins: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Markdown related settings
+pub struct MarkdownConfig {
+    /// The file extensions of markdown files, without the leading dot
+    pub extensions: Vec<String>,
+    /// Render LaTeX math within `$..$` and `$$..$$` into MathML
+    pub math: bool,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        MarkdownConfig {
+            extensions: ["md", "markdown", "mdown", "mkd"].iter().map(|e| e.to_string()).collect(),
+            math: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Plain text related settings
+pub struct TextConfig {
+    /// The file extensions of plain text files, which are shown preformatted
+    pub extensions: Vec<String>,
+}
+
+impl Default for TextConfig {
+    fn default() -> Self {
+        TextConfig { extensions: vec!["txt".to_owned()] }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings of finding the markdown files, `.wikiignore` files are always honored
+pub struct DiscoveryConfig {
+    /// Honor `.gitignore` files as well
+    pub gitignore: bool,
+    /// Only use files matching one of these patterns, relative to the input directory. Ignored
+    /// files stay ignored.
+    pub include: Vec<String>,
+    /// Skip files matching one of these patterns, relative to the input directory
+    pub exclude: Vec<String>,
+    /// Follow symbolic links to files and directories
+    pub follow_links: bool,
+    /// Use files whose symbolic links point outside of the input directory
+    pub allow_external_links: bool,
+    /// Copy all files which are no pages, like images, into the output directory
+    pub assets: bool,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            gitignore: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            follow_links: true,
+            allow_external_links: false,
+            assets: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+/// The color schemes a theme is shown in
+pub enum ColorScheme {
+    /// Follows the preference of the browser
+    Auto,
+    /// Always light
+    Light,
+    /// Always dark
+    Dark,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// The theme variables, available as CSS custom properties to every theme
+pub struct StyleConfig {
+    /// Whether pages are shown light, dark or following the browser preference
+    pub color_scheme: ColorScheme,
+    /// The color of links and highlights
+    pub accent_color: String,
+    /// The background color in light mode
+    pub background_color: String,
+    /// The text color in light mode
+    pub text_color: String,
+    /// The background color in dark mode
+    pub dark_background_color: String,
+    /// The text color in dark mode
+    pub dark_text_color: String,
+    /// The CSS font family of the text
+    pub font_family: String,
+    /// The URL of a logo shown next to the site title, relative to the output directory
+    pub logo: Option<String>,
+}
+
+impl Default for StyleConfig {
+    fn default() -> Self {
+        StyleConfig {
+            color_scheme: ColorScheme::Auto,
+            accent_color: "#0969da".to_owned(),
+            background_color: "#ffffff".to_owned(),
+            text_color: "#1f2328".to_owned(),
+            dark_background_color: "#0d1117".to_owned(),
+            dark_text_color: "#e6edf3".to_owned(),
+            font_family: "system-ui, sans-serif".to_owned(),
+            logo: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+/// An additional input directory whose pages are generated below a URL prefix
+pub struct MountConfig {
+    /// The directory containing the markdown files
+    #[serde(with = "serde_path")]
+    pub directory: PathBuf,
+    /// The URL prefix, which is the directory of the pages within the output directory
+    pub prefix: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+/// An entry of the site menu
+pub struct MenuConfig {
+    /// The text of the entry
+    pub title: String,
+    /// The link target, relative to the output directory or absolute. Entries without one only
+    /// open their children.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The position within the menu, lower weights come first
+    #[serde(default)]
+    pub weight: i64,
+    /// The entries shown as dropdown below this one
+    #[serde(default)]
+    pub children: Vec<MenuConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings of the sitemap and robots.txt, which are generated unless they are assets
+pub struct SitemapConfig {
+    /// Generate a `sitemap.xml` of all pages besides drafts and hidden ones, needs the `base_url`
+    pub enabled: bool,
+    /// Generate a `robots.txt` referencing the sitemap
+    pub robots: bool,
+    /// The paths crawlers should not visit, like `/drafts/`
+    pub disallow: Vec<String>,
+}
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        SitemapConfig {
+            enabled: true,
+            robots: true,
+            disallow: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings of the Atom, RSS and JSON feeds of recently changed pages
+pub struct FeedsConfig {
+    /// Generate the feeds of the whole site, needs the `base_url`
+    pub enabled: bool,
+    /// The maximum number of pages per feed
+    pub limit: usize,
+    /// Take the date of the last change from the git history, if there is no front matter date
+    pub git: bool,
+    /// The maximum number of characters of the summaries
+    pub summary_length: usize,
+    /// Directories relative to the output directory which get feeds of their own
+    pub sections: Vec<String>,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        FeedsConfig {
+            enabled: true,
+            limit: 20,
+            git: false,
+            summary_length: 200,
+            sections: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings of rendering fenced code blocks like ```` ```dot ```` into inline SVG with local tools
+pub struct DiagramsConfig {
+    /// Render diagram code blocks, off by default since building runs the local tools
+    pub enabled: bool,
+    /// The maximum number of seconds a tool may run per diagram
+    pub timeout: u64,
+    /// The command line of every code block language, which reads the diagram source from its
+    /// standard input and writes SVG to its standard output
+    pub commands: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for DiagramsConfig {
+    fn default() -> Self {
+        let command = |arguments: &[&str]| arguments.iter().map(|a| a.to_string()).collect();
+        let mut commands = BTreeMap::new();
+        commands.insert("dot".to_owned(), command(&["dot", "-Tsvg"]));
+        commands.insert("plantuml".to_owned(), command(&["plantuml", "-tsvg", "-pipe"]));
+        DiagramsConfig {
+            enabled: false,
+            timeout: 10,
+            commands,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings of the recent changes page, generated as `recent.html` and served at `/recent`
+pub struct RecentConfig {
+    /// Generate the page
+    pub enabled: bool,
+    /// The maximum number of changes listed
+    pub limit: usize,
+}
+
+impl Default for RecentConfig {
+    fn default() -> Self {
+        RecentConfig {
+            enabled: true,
+            limit: 50,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings of the integrated HTTP server
+pub struct ServerConfig {
+    /// The IPv4/IPv6 address or host name to bind to
+    pub address: String,
+    /// The TCP port to listen on
+    pub port: u16,
+    /// Listen on a unix domain socket at this path instead of a TCP port
+    #[serde(with = "serde_path::option")]
+    pub unix_socket: Option<PathBuf>,
+    /// Use the listening socket passed by the parent process
+    pub inherit_socket: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: "localhost".to_owned(),
+            port: 30000,
+            unix_socket: None,
+            inherit_socket: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// The binding the HTTP server should listen on
+    pub fn binding(&self) -> Binding {
+        if let Some(ref path) = self.unix_socket {
+            Binding::Unix(path.clone())
+        } else if self.inherit_socket {
+            Binding::Inherited
+        } else {
+            Binding::tcp(&self.address, self.port)
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration for the wiki in `directory`: the defaults, overridden by the
+    /// `wiki.toml` within `directory` if existing, overridden by environment variables
+    pub fn load<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        let directory = directory.as_ref();
+        let config_path = directory.join(CONFIG_FILE);
+        let mut config = if config_path.is_file() {
+            info!("Using configuration file '{}'", config_path.display());
+            Config::from_file(&config_path)?
+        } else {
+            Config { input_directory: directory.to_path_buf(), ..Config::default() }
+        };
+        config.apply_environment()?;
+        Ok(config)
+    }
+
+    /// Reads a configuration file, relative directories are resolved against its location
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut content = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .chain_err(|| ErrorKind::FileOperation("read".to_owned(), path.to_path_buf()))?;
+        let mut config: Config = toml::from_str(&content).map_err(|e| {
+            let offset = e.span().map_or(0, |span| span.start);
+            let (line, column) = line_column(content.as_bytes(), offset);
+            Error::from(ErrorKind::InvalidConfig(path.to_path_buf(), line, column,
+                                                 e.message().to_owned()))
+        })?;
+
+        if let Some(base) = path.parent() {
+            config.input_directory = resolve(base, &config.input_directory);
+            config.output_directory = resolve(base, &config.output_directory);
+            // Built-in themes are only shadowed by an existing directory of the same name
+            config.theme = config.theme.map(|theme| {
+                if is_builtin_theme(&theme) && !base.join(&theme).is_dir() {
+                    theme
+                } else {
+                    resolve(base, Path::new(&theme)).to_string_lossy().into_owned()
+                }
+            });
+            config.server.unix_socket = config.server.unix_socket.map(|p| resolve(base, &p));
+            for mount in &mut config.mounts {
+                mount.directory = resolve(base, &mount.directory);
+            }
+        }
+        Ok(config)
+    }
+
+    /// The file extensions of all source files, regardless of their renderer
+    pub fn source_extensions(&self) -> Vec<String> {
+        self.markdown.extensions.iter().chain(&self.text.extensions).cloned().collect()
+    }
+
+    /// Overrides all settings given as `WIKI_*` environment variables
+    pub fn apply_environment(&mut self) -> Result<()> {
+        self.apply_variables(env::vars_os())
+    }
+
+    /// Overrides all settings given as `WIKI_*` entries of the `variables`. Paths may be any
+    /// OS string, all other settings must be valid UTF-8.
+    pub fn apply_variables<I, K, V>(&mut self, variables: I) -> Result<()>
+        where I: IntoIterator<Item = (K, V)>, K: Into<OsString>, V: Into<OsString>
+    {
+        let mut paths = HashMap::new();
+        let mut settings = HashMap::new();
+        for (key, value) in variables {
+            let key = match key.into().into_string() {
+                Ok(key) if key.starts_with("WIKI_") => key,
+                _ => continue,
+            };
+            if PATH_VARIABLES.contains(&key.as_str()) {
+                paths.insert(key, value.into());
+            } else {
+                let value = value.into().into_string()
+                    .map_err(|_| format!("The environment variable {} is no valid UTF-8", key))?;
+                settings.insert(key, value);
+            }
+        }
+        let variables = settings;
+
+        set_path_from_variable(&mut self.input_directory, &paths, "WIKI_INPUT_DIRECTORY");
+        set_path_from_variable(&mut self.output_directory, &paths, "WIKI_OUTPUT_DIRECTORY");
+        set_path_from_variable(&mut self.file_directory, &paths, "WIKI_FILE_DIRECTORY");
+        set_from_variable(&mut self.title, &variables, "WIKI_TITLE");
+        set_from_variable(&mut self.base_url, &variables, "WIKI_BASE_URL");
+        if let Some(theme) = variables.get("WIKI_THEME") {
+            self.theme = Some(theme.clone());
+        }
+        if let Some(extensions) = variables.get("WIKI_MARKDOWN_EXTENSIONS") {
+            self.markdown.extensions = extensions.split(',').map(|e| e.trim().to_owned()).collect();
+        }
+        if let Some(extensions) = variables.get("WIKI_TEXT_EXTENSIONS") {
+            self.text.extensions = extensions.split(',').map(|e| e.trim().to_owned()).collect();
+        }
+        set_from_variable(&mut self.server.address, &variables, "WIKI_SERVER_ADDRESS");
+        if let Some(port) = variables.get("WIKI_SERVER_PORT") {
+            self.server.port = port.parse()
+                .map_err(|e| format!("Invalid WIKI_SERVER_PORT '{}': {}", port, e))?;
+        }
+        if let Some(path) = paths.get("WIKI_SERVER_UNIX_SOCKET") {
+            self.server.unix_socket = Some(PathBuf::from(path));
+        }
+        Ok(())
+    }
+}
+
+/// Overrides `value` with the variable `key` if it is set
+fn set_from_variable(value: &mut String, variables: &HashMap<String, String>, key: &str) {
+    if let Some(variable) = variables.get(key) {
+        *value = variable.clone();
+    }
+}
+
+/// Overrides the path `value` with the variable `key` if it is set
+fn set_path_from_variable(value: &mut PathBuf, variables: &HashMap<String, OsString>, key: &str) {
+    if let Some(variable) = variables.get(key) {
+        *value = PathBuf::from(variable);
+    }
+}
+
+/// Resolves a relative `path` against the `base` directory
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+    if base.as_os_str().is_empty() || path.is_absolute() {
+        path.to_path_buf()
+    } else if path == Path::new(".") {
+        base.to_path_buf()
+    } else {
+        base.join(path)
+    }
+}
@@ -0,0 +1,102 @@
+//! Everything related to the dependencies between pages
+//!
+//! A page depends on every page it links to, so renaming or changing the target re-renders it.
+
+use encoding::path_from_link;
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Extracts the source files referenced by inline links (`[text](target.md)`) and reference
+/// definitions (`[label]: target.md`) within `content` of the page `source`. Only relative
+/// targets with one of the given source `extensions` are considered, code blocks are skipped.
+pub fn extract_links(source: &Path, content: &str, extensions: &[String]) -> Vec<PathBuf> {
+    let base = source.parent().unwrap_or_else(|| Path::new(""));
+    let mut links = Vec::new();
+    let mut in_code_block = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.starts_with("    ") || line.starts_with('\t') {
+            continue;
+        }
+
+        let mut targets = Vec::new();
+
+        // Reference definitions like `[label]: target "title"`
+        if trimmed.starts_with('[') {
+            if let Some(end) = trimmed.find("]:") {
+                targets.push(&trimmed[end + 2..]);
+            }
+        }
+
+        // Inline links like `[text](target "title")`
+        let mut rest = line;
+        while let Some(start) = rest.find("](") {
+            rest = &rest[start + 2..];
+            let end = rest.find(')').unwrap_or(rest.len());
+            targets.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        for target in targets {
+            if let Some(link) = link_target(target, extensions) {
+                let path = normalize(&base.join(path_from_link(link)));
+                if !links.contains(&path) {
+                    links.push(path);
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// Extracts the local source file of a link target, stripping titles, anchors and queries. The
+/// target may be percent-encoded.
+fn link_target<'a>(target: &'a str, extensions: &[String]) -> Option<&'a str> {
+    let target = target
+        .split_whitespace()
+        .next()?
+        .trim_start_matches('<')
+        .trim_end_matches('>');
+    let target = target.split(['#', '?']).next()?;
+
+    if target.is_empty() || target.starts_with('/') || target.contains(':') {
+        return None;
+    }
+    let extension = Path::new(target).extension()?.to_str()?;
+    if extensions.iter().any(|e| e == extension) {
+        Some(target)
+    } else {
+        None
+    }
+}
+
+/// Lexically normalizes `path` by resolving `.` and `..` components
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Returns true if any of the `dependencies` is within the `changed` pages
+pub fn is_affected(dependencies: &[PathBuf], changed: &HashSet<PathBuf>) -> bool {
+    dependencies.iter().any(|dependency| changed.contains(dependency))
+}
@@ -0,0 +1,278 @@
+//! Everything related to rendering diagram code blocks into inline SVG
+//!
+//! Fenced code blocks of a configured language, like ```` ```dot ````, are taken out of the
+//! markdown before it is rendered. Their source is piped through the configured local command,
+//! whose SVG output replaces the block afterwards. Outputs are cached by the hash of the command
+//! and the source, so unchanged diagrams are not rendered again, and removed once no page uses
+//! them. If the tool is missing, fails or times out, the source is shown instead and a warning is
+//! reported, and the page is rendered again with the next build.
+
+use config::DiagramsConfig;
+use filehash::Filehash;
+use renderer::{escape_html, Placeholders};
+use wait_timeout::ChildExt;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// The directory within the output directory where rendered diagrams are cached
+pub const DIAGRAM_CACHE: &str = ".diagrams";
+
+/// Distinguishes the temporary files of concurrently cached diagrams
+static TEMPORARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A diagram code block taken out of the markdown source
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagram {
+    /// The language of the code block, like `dot`
+    pub language: String,
+    /// The source of the diagram
+    pub source: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The configured diagram tools
+pub struct Diagrams {
+    commands: BTreeMap<String, Vec<String>>,
+    timeout: Duration,
+    cache: Option<PathBuf>,
+}
+
+impl Diagrams {
+    /// The diagram tools of the `config`, caching their outputs within `cache` if given
+    pub fn new(config: &DiagramsConfig, cache: Option<PathBuf>) -> Self {
+        Diagrams {
+            commands: if config.enabled {
+                config.commands.iter()
+                               .filter(|&(_, command)| !command.is_empty())
+                               .map(|(language, command)| (language.clone(), command.clone()))
+                               .collect()
+            } else {
+                BTreeMap::new()
+            },
+            timeout: Duration::from_secs(config.timeout),
+            cache,
+        }
+    }
+
+    /// Takes all diagram code blocks out of the markdown `content`, replacing them by the
+    /// `placeholders`. Other code blocks are kept as they are.
+    pub fn extract(&self, content: &str, placeholders: &Placeholders) -> (String, Vec<Diagram>) {
+        let mut result = String::with_capacity(content.len());
+        let mut diagrams = Vec::new();
+        if self.commands.is_empty() {
+            return (content.to_owned(), diagrams);
+        }
+
+        // The fence and language of the code block at the current line, if any
+        let mut block: Option<(String, Option<String>)> = None;
+        let mut source = String::new();
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim_start_matches(' ');
+            let is_fence = line.len() - trimmed.len() < 4 &&
+                           (trimmed.starts_with("```") || trimmed.starts_with("~~~"));
+            match block.take() {
+                None if is_fence => {
+                    let marker = &trimmed[..1];
+                    let fence_length = trimmed.len() - trimmed.trim_start_matches(marker).len();
+                    let fence = trimmed[..fence_length].to_owned();
+                    let language = trimmed[fence_length..].split_whitespace().next()
+                                                          .filter(|l| self.commands.contains_key(*l))
+                                                          .map(str::to_owned);
+                    if language.is_none() {
+                        result.push_str(line);
+                    }
+                    block = Some((fence, language));
+                }
+                None => result.push_str(line),
+                Some((fence, language)) => {
+                    // A block is closed by a fence of the same character, at least as long
+                    let closing = trimmed.trim_end();
+                    let closes = is_fence && closing.len() >= fence.len() &&
+                                 closing.trim_start_matches(&fence[..1]).is_empty();
+                    match language {
+                        Some(language) if closes => {
+                            result.push_str(&format!("\n{}\n\n",
+                                                     placeholders.get(diagrams.len())));
+                            diagrams.push(Diagram { language, source: source.clone() });
+                            source.clear();
+                        }
+                        Some(language) => {
+                            source.push_str(line);
+                            block = Some((fence, Some(language)));
+                        }
+                        None => {
+                            result.push_str(line);
+                            if !closes {
+                                block = Some((fence, None));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // An unclosed diagram block lasts until the end
+        if let Some((_, Some(language))) = block {
+            result.push_str(&format!("\n{}\n\n", placeholders.get(diagrams.len())));
+            diagrams.push(Diagram { language, source });
+        }
+        (result, diagrams)
+    }
+
+    /// Replaces the `placeholders` within the rendered `html` by the SVG of the `diagrams`.
+    /// Diagrams which could not be rendered are shown as source and their errors are added to
+    /// `warnings`, the cache files of the others to `cached`.
+    pub fn insert(&self, html: &str, diagrams: &[Diagram], placeholders: &Placeholders,
+                  warnings: &mut Vec<String>, cached: &mut Vec<String>) -> String {
+        let mut html = html.to_owned();
+        for (index, diagram) in diagrams.iter().enumerate() {
+            let rendered = match self.render(&diagram.language, &diagram.source) {
+                Ok(svg) => {
+                    if self.cache.is_some() {
+                        cached.push(self.cache_file(&diagram.language, &diagram.source));
+                    }
+                    format!("<figure class=\"diagram\">{}</figure>", svg)
+                }
+                Err(message) => {
+                    warnings.push(format!("Unable to render the {} diagram: {}",
+                                          diagram.language, message));
+                    format!("<pre class=\"diagram-source\"><code>{}</code></pre>",
+                            escape_html(&diagram.source))
+                }
+            };
+            html = placeholders.replace(&html, index, &rendered, true);
+        }
+        html
+    }
+
+    /// Renders the diagram `source` of the `language` into SVG, using the cache if possible
+    pub fn render(&self, language: &str, source: &str) -> Result<String, String> {
+        let command = self.commands.get(language)
+                                   .ok_or_else(|| format!("No command for '{}'", language))?;
+        let cached = self.cache.as_ref()
+                               .map(|cache| cache.join(self.cache_file(language, source)));
+        if let Some(svg) = cached.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            return Ok(svg);
+        }
+
+        let svg = self.run(command, source)?;
+        if let Some(path) = cached {
+            if let Err(e) = store(&path, &svg) {
+                warn!("Unable to cache the diagram '{}': {}", path.display(), e);
+            }
+        }
+        Ok(svg)
+    }
+
+    /// The name of the cache file of the diagram `source` of the `language`, keyed by the hash of
+    /// its command and source
+    fn cache_file(&self, language: &str, source: &str) -> String {
+        let command = self.commands.get(language).map(|command| command.join("\0"));
+        let key = Filehash::hash(format!("{}\0{}", command.unwrap_or_default(), source).as_bytes());
+        format!("{}.svg", key)
+    }
+
+    /// Removes all files from the cache besides the `used` ones, so diagrams which are no longer
+    /// part of any page do not pile up
+    pub fn prune<'a, I: IntoIterator<Item = &'a String>>(&self, used: I) {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return,
+        };
+        let used: HashSet<&String> = used.into_iter().collect();
+        let entries = match fs::read_dir(cache) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if used.contains(&name) {
+                continue;
+            }
+            debug!("Removing the unused cached diagram '{}'", entry.path().display());
+            if let Err(e) = fs::remove_file(entry.path()) {
+                warn!("Unable to remove the cached diagram '{}': {}", entry.path().display(), e);
+            }
+        }
+    }
+
+    /// Pipes the `source` through the `command` and returns its SVG output
+    fn run(&self, command: &[String], source: &str) -> Result<String, String> {
+        let mut child = Command::new(&command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => format!("'{}' is not installed", command[0]),
+                _ => format!("Unable to run '{}': {}", command[0], e),
+            })?;
+
+        // The pipes are served by threads, so neither side blocks on a full buffer
+        let mut stdin = child.stdin.take();
+        let input = source.to_owned();
+        let writer = thread::spawn(move || {
+            if let Some(ref mut stdin) = stdin {
+                // A tool may exit without reading everything, its status tells about failures
+                let _ = stdin.write_all(input.as_bytes());
+            }
+        });
+        let stdout = read_in_background(child.stdout.take());
+        let stderr = read_in_background(child.stderr.take());
+
+        let status = match child.wait_timeout(self.timeout) {
+            Ok(Some(status)) => status,
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("'{}' timed out after {} seconds", command[0],
+                                   self.timeout.as_secs()));
+            }
+            Err(e) => return Err(format!("Unable to wait for '{}': {}", command[0], e)),
+        };
+        let _ = writer.join();
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(format!("'{}' failed with {}: {}", command[0], status,
+                               String::from_utf8_lossy(&stderr).trim()));
+        }
+        let output = String::from_utf8_lossy(&stdout);
+        match output.find("<svg") {
+            // The XML declaration and doctype are not allowed within HTML
+            Some(start) => Ok(output[start..].trim_end().to_owned()),
+            None => Err(format!("'{}' produced no SVG", command[0])),
+        }
+    }
+}
+
+/// Reads the whole `pipe` within a thread
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// Stores the `svg` at `path` atomically, since pages are rendered in parallel
+fn store(path: &Path, svg: &str) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let temporary = path.with_extension(format!("tmp{}",
+                                                TEMPORARY_COUNTER.fetch_add(1, Ordering::SeqCst)));
+    fs::write(&temporary, svg)?;
+    fs::rename(&temporary, path)
+}
@@ -0,0 +1,221 @@
+//! Everything related to finding the source files of a wiki
+//!
+//! Files are skipped if they match a `.wikiignore` (gitignore syntax) in the input directory or
+//! any of its subdirectories, a `.gitignore` if enabled, or the exclude patterns of the
+//! configuration. If include patterns are configured, only the remaining files matching one of
+//! them are used. Symbolic links are followed if enabled: loops are skipped, files reached
+//! through several paths are used once, preferring the path without links, and links leaving
+//! the input directory are refused unless allowed, also when they point to directories. All
+//! other files besides hidden ones, the configuration and the output and theme directories are
+//! static assets, which are copied into the output.
+
+use config::{Config, CONFIG_FILE};
+use dependency::normalize;
+use renderer::Renderer;
+use error::*;
+use ignore::{self, WalkBuilder};
+use ignore::overrides::OverrideBuilder;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::canonicalize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the ignore files searched in the input directory
+pub const IGNORE_FILE: &str = ".wikiignore";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The files found within an input directory, sorted by their path
+pub struct Sources {
+    /// The source files of pages
+    pub pages: Vec<PathBuf>,
+    /// The static assets, which are copied as they are
+    pub assets: Vec<PathBuf>,
+}
+
+/// Finds all source files within `directory`, sorted by their path
+pub fn find_pages(directory: &Path, config: &Config) -> Result<Vec<PathBuf>> {
+    find_sources(directory, config, &config.output_directory)
+        .map(|sources| sources.pages)
+}
+
+/// Finds all source files and static assets within `directory`, skipping the files generated
+/// into `output_directory`
+pub fn find_sources(directory: &Path, config: &Config, output_directory: &Path)
+                    -> Result<Sources> {
+    let discovery = &config.discovery;
+
+    // Generated or theme files must never be treated as assets
+    let skipped_directories: Vec<PathBuf> = Some(output_directory)
+        .into_iter()
+        .chain(config.theme.as_ref().map(Path::new))
+        .filter_map(|directory| canonicalize(directory).ok())
+        .collect();
+
+    // Include patterns only select among the files which are not ignored, so they are matched
+    // separately instead of overriding the ignore files
+    let includes = if discovery.include.is_empty() {
+        None
+    } else {
+        let mut includes = OverrideBuilder::new(directory);
+        for pattern in &discovery.include {
+            includes.add(pattern)?;
+        }
+        Some(includes.build()?)
+    };
+
+    // Exclude patterns are negated
+    let mut overrides = OverrideBuilder::new(directory);
+    for pattern in &discovery.exclude {
+        overrides.add(&format!("!{}", pattern))?;
+    }
+
+    let root = canonicalize(directory)?;
+    let walk_root = root.clone();
+    let allow_external_links = discovery.allow_external_links;
+
+    let mut walker = WalkBuilder::new(directory);
+    walker.standard_filters(false)
+          .git_ignore(discovery.gitignore)
+          .git_exclude(discovery.gitignore)
+          .require_git(false)
+          .follow_links(discovery.follow_links)
+          .add_custom_ignore_filename(IGNORE_FILE)
+          .overrides(overrides.build()?)
+          .filter_entry(move |entry| {
+              if !entry.file_type().is_some_and(|file_type| file_type.is_dir()) {
+                  return true;
+              }
+              let path = match canonicalize(entry.path()) {
+                  Ok(path) => path,
+                  Err(_) => return true,
+              };
+              if entry.path_is_symlink() && !path.starts_with(&walk_root) && !allow_external_links {
+                  warn!("Skipping '{}', it links to '{}' outside of the input directory",
+                        entry.path().display(), path.display());
+                  return false;
+              }
+              !skipped_directories.contains(&path)
+          })
+          .sort_by_file_name(|a, b| a.cmp(b));
+
+    // The files found, in order, and the index of the file used for each resolved target
+    let mut found: Vec<(PathBuf, bool)> = Vec::new();
+    let mut used: HashMap<PathBuf, usize> = HashMap::new();
+    for entry in walker.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(ref e) if is_link_error(e) => {
+                warn!("Skipping symbolic link: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+            continue;
+        }
+        if let Some(ref includes) = includes {
+            if !includes.matched(entry.path(), false).is_whitelist() {
+                trace!("Skipping '{}', it matches no include pattern", entry.path().display());
+                continue;
+            }
+        }
+        let is_page = Renderer::for_path(entry.path(), config).is_some();
+        if !is_page && !is_asset(entry.path(), directory, discovery.assets) {
+            trace!("Skipping '{}', it is neither a page nor an asset", entry.path().display());
+            continue;
+        }
+
+        // Resolve all symbolic links to find files reached through several paths
+        let target = canonicalize(entry.path())?;
+        if !target.starts_with(&root) && !discovery.allow_external_links {
+            warn!("Skipping '{}', it links to '{}' outside of the input directory",
+                  entry.path().display(), target.display());
+            continue;
+        }
+
+        // The file itself is preferred over the links to it
+        let is_real = is_real_path(entry.path(), directory, &root, &target);
+        let index = found.len();
+        match used.get(&target).cloned() {
+            Some(previous) if is_real => {
+                debug!("Skipping '{}', it links to '{}'", found[previous].0.display(),
+                       entry.path().display());
+                used.insert(target, index);
+            }
+            Some(_) => {
+                debug!("Skipping '{}', the file was already found through another path",
+                       entry.path().display());
+                continue;
+            }
+            None => {
+                used.insert(target, index);
+            }
+        }
+        found.push((entry.into_path(), is_page));
+    }
+
+    let used: HashSet<usize> = used.into_values().collect();
+    let mut sources = Sources::default();
+    for (index, (path, is_page)) in found.into_iter().enumerate() {
+        if !used.contains(&index) {
+            continue;
+        } else if is_page {
+            sources.pages.push(path);
+        } else {
+            sources.assets.push(path);
+        }
+    }
+    Ok(sources)
+}
+
+/// Returns true if the file `path` within `directory` is not reached through a symbolic link,
+/// so its resolved `target` is the same path below the canonical `root`
+fn is_real_path(path: &Path, directory: &Path, root: &Path, target: &Path) -> bool {
+    path.strip_prefix(directory)
+        .map(|relative| root.join(relative) == target)
+        .unwrap_or(false)
+}
+
+/// Returns true if the file `path` within `directory` is a static asset
+fn is_asset(path: &Path, directory: &Path, assets: bool) -> bool {
+    let relative_path = path.strip_prefix(directory).unwrap_or(path);
+    let hidden = relative_path.components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+    assets && !hidden && relative_path != Path::new(CONFIG_FILE)
+}
+
+/// The path of the file `path` relative to the input `directory` it was found in
+pub fn relative_path(path: &Path, directory: &Path) -> Result<PathBuf> {
+    // Files found through symbolic links keep the path they were found at
+    let file_buf = normalize(path);
+    let input_root_buf = normalize(directory);
+    match file_buf.strip_prefix(&input_root_buf) {
+        Ok(relative_path) if relative_path.is_relative() => {
+            return Ok(relative_path.to_path_buf())
+        }
+        _ => {}
+    }
+
+    // Get canonical normal forms of the input path and the recursively
+    // searched directories
+    let file_buf_n = canonicalize(path)?;
+    let input_root_buf_n = canonicalize(directory)?;
+
+    // Reduce the input dir
+    let relative_path = file_buf_n.strip_prefix(&input_root_buf_n)
+        .map_err(|_| format!("'{}' is not within the input directory '{}'",
+                             path.display(), directory.display()))?;
+    Ok(relative_path.to_path_buf())
+}
+
+/// Returns true if `error` is caused by a loop or a dangling target of symbolic links
+fn is_link_error(error: &ignore::Error) -> bool {
+    match *error {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { ref err, .. } |
+        ignore::Error::WithDepth { ref err, .. } => is_link_error(err),
+        ignore::Error::Io(ref e) => e.kind() == io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
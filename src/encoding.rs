@@ -0,0 +1,161 @@
+//! Everything related to encoding paths losslessly
+//!
+//! File names are not required to be valid UTF-8. Paths are therefore kept as `Path` within the
+//! wiki, percent-encoded within URLs and stored as raw bytes within the manifest if they are no
+//! valid UTF-8.
+
+use percent_encoding::{percent_decode, percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+
+/// The percent-encoded URL path of the relative `path`, with `/` as separator
+pub fn url_path(path: &Path) -> String {
+    path.components()
+        .map(|component| {
+            percent_encode(&os_str_to_bytes(component.as_os_str()), PATH_SEGMENT_ENCODE_SET)
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Decodes the percent-encoded URL path `segments` into a relative path. Segments which would
+/// leave the directory the path is relative to, like `..`, result in `None`.
+pub fn path_from_url<S: AsRef<str>>(segments: &[S]) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for segment in segments {
+        let bytes: Vec<u8> = percent_decode(segment.as_ref().as_bytes()).collect();
+        if bytes.is_empty() {
+            continue;
+        }
+        if bytes.contains(&b'/') || bytes.contains(&b'\\') || bytes.contains(&0) {
+            return None;
+        }
+        let decoded = os_string_from_bytes(bytes);
+        match Path::new(&decoded).components().next() {
+            Some(Component::Normal(_)) => path.push(decoded),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// Decodes the percent-encoded relative link `target` into a path
+pub fn path_from_link(target: &str) -> PathBuf {
+    PathBuf::from(os_string_from_bytes(percent_decode(target.as_bytes()).collect()))
+}
+
+#[cfg(unix)]
+/// The raw bytes of `os_str`
+pub fn os_str_to_bytes(os_str: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    os_str.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+/// The raw bytes of `os_str`, which are only lossless for valid unicode on this platform
+pub fn os_str_to_bytes(os_str: &OsStr) -> Vec<u8> {
+    os_str.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+/// The `OsString` of the raw `bytes`
+pub fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+/// The `OsString` of the raw `bytes`, which are only lossless for valid unicode on this platform
+pub fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Serializes paths as string if they are valid UTF-8 and as array of raw bytes otherwise, to be
+/// used with `#[serde(with = "encoding::serde_path")]`
+pub mod serde_path {
+    use super::{os_str_to_bytes, os_string_from_bytes};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::{Path, PathBuf};
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(untagged)]
+    /// The serialized form of a path
+    pub enum RawPath {
+        /// A valid UTF-8 path
+        Utf8(String),
+        /// A path which is no valid UTF-8
+        Bytes(Vec<u8>),
+    }
+
+    impl<'a> From<&'a Path> for RawPath {
+        fn from(path: &'a Path) -> Self {
+            match path.to_str() {
+                Some(path) => RawPath::Utf8(path.to_owned()),
+                None => RawPath::Bytes(os_str_to_bytes(path.as_os_str())),
+            }
+        }
+    }
+
+    impl From<RawPath> for PathBuf {
+        fn from(raw: RawPath) -> Self {
+            match raw {
+                RawPath::Utf8(path) => PathBuf::from(path),
+                RawPath::Bytes(bytes) => PathBuf::from(os_string_from_bytes(bytes)),
+            }
+        }
+    }
+
+    /// Serializes a single path
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        RawPath::from(path).serialize(serializer)
+    }
+
+    /// Deserializes a single path
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        RawPath::deserialize(deserializer).map(PathBuf::from)
+    }
+
+    /// The same for optional paths
+    pub mod option {
+        use super::RawPath;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use std::path::PathBuf;
+
+        /// Serializes an optional path
+        pub fn serialize<S: Serializer>(path: &Option<PathBuf>, serializer: S)
+                                        -> Result<S::Ok, S::Error> {
+            path.as_ref().map(|path| RawPath::from(path.as_path())).serialize(serializer)
+        }
+
+        /// Deserializes an optional path
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Option<PathBuf>, D::Error> {
+            Option::<RawPath>::deserialize(deserializer).map(|path| path.map(PathBuf::from))
+        }
+    }
+
+    /// The same for a list of paths
+    pub mod vec {
+        use super::RawPath;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use std::path::PathBuf;
+
+        /// Serializes a list of paths
+        pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S)
+                                        -> Result<S::Ok, S::Error> {
+            paths.iter()
+                 .map(|path| RawPath::from(path.as_path()))
+                 .collect::<Vec<_>>()
+                 .serialize(serializer)
+        }
+
+        /// Deserializes a list of paths
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Vec<PathBuf>, D::Error> {
+            Vec::<RawPath>::deserialize(deserializer)
+                .map(|paths| paths.into_iter().map(PathBuf::from).collect())
+        }
+    }
+}
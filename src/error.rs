@@ -2,7 +2,9 @@
 
 use std::io;
 use glob;
-use iron::error::{HttpError, IronError};
+use ignore;
+use iron::error::HttpError;
+use std::path::PathBuf;
 
 error_chain! {
     foreign_links {
@@ -10,5 +12,110 @@ error_chain! {
         Glob(glob::GlobError) #[doc="A glob error"];
         Pattern(glob::PatternError) #[doc="A glob pattern error"];
         Http(HttpError) #[doc="A http error"];
+        Ignore(ignore::Error) #[doc="An error of ignore files or patterns"];
     }
+
+    errors {
+        #[doc="The input directory does not exist"]
+        InputDirectoryMissing(path: PathBuf) {
+            description("input directory missing")
+            display("The input directory '{}' does not exist", path.display())
+        }
+
+        #[doc="No file of the input directory matched the discovery patterns of pages"]
+        NoInputFiles(path: PathBuf) {
+            description("no input files")
+            display("No pages found in '{}': no file matched the discovery patterns",
+                    path.display())
+        }
+
+        #[doc="The pages are rendered from another directory than they were read from"]
+        InputDirectoryMismatch(given: PathBuf, read: PathBuf) {
+            description("input directory mismatch")
+            display("'{}' is not the input directory '{}' the pages were read from",
+                    given.display(), read.display())
+        }
+
+        #[doc="An operation like reading or writing failed on a file or directory"]
+        FileOperation(operation: String, path: PathBuf) {
+            description("file operation failed")
+            display("Unable to {} '{}'", operation, path.display())
+        }
+
+        #[doc="A file is not valid UTF-8, at the given line and column"]
+        InvalidUtf8(path: PathBuf, line: usize, column: usize) {
+            description("invalid UTF-8")
+            display("'{}' is not valid UTF-8 at line {}, column {}", path.display(), line, column)
+        }
+
+        #[doc="Several source files would be generated into the same output file"]
+        OutputConflict(output: PathBuf, first: PathBuf, second: PathBuf) {
+            description("conflicting output files")
+            display("Both '{}' and '{}' would be generated into '{}'",
+                    first.display(), second.display(), output.display())
+        }
+
+        #[doc="A mount prefix is not a relative path within the output directory"]
+        InvalidMountPrefix(prefix: String) {
+            description("invalid mount prefix")
+            display("The mount prefix '{}' must be a relative path without '..'", prefix)
+        }
+
+        #[doc="A page could not be rendered"]
+        RenderFailed(path: PathBuf) {
+            description("render failed")
+            display("Unable to render '{}'", path.display())
+        }
+
+        #[doc="The configuration file is invalid, at the given line and column"]
+        InvalidConfig(path: PathBuf, line: usize, column: usize, message: String) {
+            description("invalid configuration")
+            display("Invalid configuration '{}' at line {}, column {}: {}",
+                    path.display(), line, column, message)
+        }
+
+        #[doc="The front matter of a page is no valid YAML or has invalid fields"]
+        InvalidFrontMatter(path: PathBuf, message: String) {
+            description("invalid front matter")
+            display("Invalid front matter in '{}': {}", path.display(), message)
+        }
+
+        #[doc="A page or wiki which should be created already exists"]
+        AlreadyExists(path: PathBuf) {
+            description("already exists")
+            display("'{}' already exists", path.display())
+        }
+
+        #[doc="The output directory contains the input directory and must not be removed"]
+        OutputContainsInput(output: PathBuf, input: PathBuf) {
+            description("output directory contains the input directory")
+            display("Refusing to remove '{}', it contains the input directory '{}'",
+                    output.display(), input.display())
+        }
+
+        #[doc="The server address could not be resolved"]
+        AddressUnresolved(address: String) {
+            description("address could not be resolved")
+            display("The address '{}' could not be resolved", address)
+        }
+
+        #[doc="The server address is already in use"]
+        AddressInUse(address: String) {
+            description("address already in use")
+            display("The address '{}' is already in use, is another server running?", address)
+        }
+    }
+}
+
+/// The message of `error` followed by the messages of all its causes
+pub fn full_message(error: &Error) -> String {
+    error.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(": ")
+}
+
+/// The line and column (both starting at 1) of the byte `offset` within `content`
+pub fn line_column(content: &[u8], offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+    let line_start = before.iter().rposition(|&byte| byte == b'\n').map_or(0, |i| i + 1);
+    (line, String::from_utf8_lossy(&before[line_start..]).chars().count() + 1)
 }
@@ -0,0 +1,252 @@
+//! Everything related to the feeds of recently changed pages
+//!
+//! Every feed is generated as Atom, RSS and JSON Feed, for the whole site and for each configured
+//! section. Pages are ordered by the date of their last change, which is taken from the front
+//! matter, the git history if enabled, or the modification time of the file. Summaries are taken
+//! from the front matter description or the first paragraph of the page.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use encoding::url_path;
+use error::*;
+use markdown::to_html;
+use renderer::escape_html;
+use serde_json;
+use sitemap::absolute_url;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The file name of the Atom feed
+pub const ATOM_FILE: &str = "atom.xml";
+
+/// The file name of the RSS feed
+pub const RSS_FILE: &str = "rss.xml";
+
+/// The file name of the JSON feed
+pub const JSON_FILE: &str = "feed.json";
+
+#[derive(Clone, Debug, PartialEq)]
+/// A page listed in a feed
+pub struct FeedEntry {
+    /// The title of the page
+    pub title: String,
+    /// The path of the page relative to the output directory
+    pub output: PathBuf,
+    /// The date of the last change
+    pub updated: DateTime<Utc>,
+    /// A short plain text summary of the page
+    pub summary: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A feed of the site or one of its sections
+pub struct Feed {
+    /// The title of the feed
+    pub title: String,
+    /// The URL the site is published at
+    pub base_url: String,
+    /// The directory of the feed files relative to the output directory, empty for the site
+    pub directory: PathBuf,
+    /// The pages of the feed, most recently changed first
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Serialize)]
+/// The JSON Feed document
+struct JsonFeed<'a> {
+    version: &'a str,
+    title: &'a str,
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<JsonFeedItem<'a>>,
+}
+
+#[derive(Serialize)]
+/// A single item of the JSON Feed
+struct JsonFeedItem<'a> {
+    id: String,
+    url: String,
+    title: &'a str,
+    summary: &'a str,
+    date_modified: String,
+}
+
+impl Feed {
+    /// The absolute URL of a page or file at `path` relative to the output directory
+    fn url(&self, path: &Path) -> String {
+        absolute_url(&self.base_url, &url_path(path))
+    }
+
+    /// The absolute URL of the feed file `name`
+    fn feed_url(&self, name: &str) -> String {
+        self.url(&self.directory.join(name))
+    }
+
+    /// The absolute URL of the index page of the feed directory
+    fn home_url(&self) -> String {
+        self.url(&self.directory.join("index.html"))
+    }
+
+    /// The date of the most recent change
+    fn updated(&self) -> DateTime<Utc> {
+        self.entries.iter().map(|entry| entry.updated).max().unwrap_or_else(Utc::now)
+    }
+
+    /// The feed in Atom format
+    pub fn atom(&self) -> String {
+        let mut xml = format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                               <feed xmlns=\"http://www.w3.org/2005/Atom\">\n  \
+                               <title>{title}</title>\n  \
+                               <id>{id}</id>\n  \
+                               <link href=\"{home}\"/>\n  \
+                               <link rel=\"self\" href=\"{id}\"/>\n  \
+                               <updated>{updated}</updated>\n  \
+                               <author><name>{title}</name></author>\n",
+                              title = escape_html(&self.title),
+                              id = escape_html(&self.feed_url(ATOM_FILE)),
+                              home = escape_html(&self.home_url()),
+                              updated = rfc3339(&self.updated()));
+        for entry in &self.entries {
+            let url = escape_html(&self.url(&entry.output));
+            xml.push_str(&format!("  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    \
+                                   <link href=\"{}\"/>\n    <updated>{}</updated>\n    \
+                                   <summary>{}</summary>\n  </entry>\n",
+                                  escape_html(&entry.title), url, url, rfc3339(&entry.updated),
+                                  escape_html(&entry.summary)));
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    /// The feed in RSS 2.0 format
+    pub fn rss(&self) -> String {
+        let mut xml = format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                               <rss version=\"2.0\">\n<channel>\n  \
+                               <title>{}</title>\n  <link>{}</link>\n  \
+                               <description>{}</description>\n  \
+                               <lastBuildDate>{}</lastBuildDate>\n",
+                              escape_html(&self.title), escape_html(&self.home_url()),
+                              escape_html(&self.title), self.updated().to_rfc2822());
+        for entry in &self.entries {
+            let url = escape_html(&self.url(&entry.output));
+            xml.push_str(&format!("  <item>\n    <title>{}</title>\n    <link>{}</link>\n    \
+                                   <guid>{}</guid>\n    <pubDate>{}</pubDate>\n    \
+                                   <description>{}</description>\n  </item>\n",
+                                  escape_html(&entry.title), url, url,
+                                  entry.updated.to_rfc2822(), escape_html(&entry.summary)));
+        }
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    /// The feed in JSON Feed 1.1 format
+    pub fn json(&self) -> Result<String> {
+        let feed = JsonFeed {
+            version: "https://jsonfeed.org/version/1.1",
+            title: &self.title,
+            home_page_url: self.home_url(),
+            feed_url: self.feed_url(JSON_FILE),
+            items: self.entries.iter()
+                .map(|entry| JsonFeedItem {
+                    id: self.url(&entry.output),
+                    url: self.url(&entry.output),
+                    title: &entry.title,
+                    summary: &entry.summary,
+                    date_modified: rfc3339(&entry.updated),
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&feed)
+            .map_err(|e| format!("Unable to serialize the JSON feed: {}", e).into())
+    }
+}
+
+/// Formats `date` for feeds, like `2017-05-04T19:00:00Z`
+fn rfc3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The plain text of the first paragraph of the markdown `content`, shortened to `length`
+/// characters at a word boundary
+pub fn summary(content: &str, length: usize) -> String {
+    let paragraph = content.split("\n\n")
+        .map(str::trim)
+        .find(|block| !block.is_empty() && !block.starts_with(['#', '`', '<', '|', '!']))
+        .unwrap_or("");
+    let text = decode_entities(&strip_tags(&to_html(paragraph)));
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= length {
+        return text;
+    }
+    let mut shortened: String = text.chars().take(length).collect();
+    if let Some(space) = shortened.rfind(' ') {
+        shortened.truncate(space);
+    }
+    shortened + "…"
+}
+
+/// Removes all HTML tags from `html`
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Decodes the entities produced by the markdown renderer
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The date of the last commit of every file within the git repository at `directory`, keyed
+/// by the path relative to `directory`. Outside of a repository or without git, no dates are
+/// known.
+pub fn git_dates(directory: &Path) -> HashMap<PathBuf, DateTime<Utc>> {
+    let mut dates = HashMap::new();
+    let output = match Command::new("git").arg("-C").arg(directory)
+                                          .args(["log", "--relative", "--name-only", "-z",
+                                                 "--no-renames", "--format=%x00%cI", "--", "."])
+                                          .output() {
+        Ok(ref output) if output.status.success() => output.stdout.clone(),
+        Ok(output) => {
+            debug!("No git history in '{}': {}", directory.display(),
+                   String::from_utf8_lossy(&output.stderr).trim());
+            return dates;
+        }
+        Err(e) => {
+            debug!("Unable to run git in '{}': {}", directory.display(), e);
+            return dates;
+        }
+    };
+
+    // The log is ordered by date, so the first commit of a file is its most recent change. With
+    // `-z` the file names are neither quoted nor escaped, but separated by NUL characters, and
+    // every commit starts with an empty field followed by its date.
+    let log = String::from_utf8_lossy(&output);
+    let mut date = None;
+    let mut expect_date = false;
+    for field in log.split('\0') {
+        if field.is_empty() {
+            expect_date = true;
+        } else if expect_date {
+            date = DateTime::parse_from_rfc3339(field).ok().map(|date| date.with_timezone(&Utc));
+            expect_date = false;
+        } else if let Some(date) = date {
+            // The first file of a commit follows the newline after the date
+            dates.entry(PathBuf::from(field.trim_start_matches('\n'))).or_insert(date);
+        }
+    }
+    dates
+}
@@ -1,89 +1,311 @@
 //! Everything related to wikis filehash functionality
+//!
+//! The state of the last build is stored as a versioned JSON manifest in the output directory.
+//! Manifests of older versions (`.files.sha` with `<hash>:<file>` lines) are migrated
+//! automatically.
 
-use InputPaths;
+use {Asset, InputPaths};
+use chrono::Utc;
+use encoding::serde_path;
 use error::*;
+use serde_json;
+use sha2::{Digest, Sha256};
 use std::fs::{self, File};
-use std::path::Path;
-use std::io::{Write, Read, BufReader, BufRead};
-use uuid::{Uuid, NAMESPACE_DNS};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::io::{Write, BufReader, BufRead};
 
-pub struct Filehash;
+/// The name of the manifest file within the output directory
+pub const MANIFEST_FILE: &str = ".manifest.json";
 
-impl Filehash {
-    /// Reads the file hash for the file specified by `file_str` out of `hash_file_str`
-    fn read_file_hash(hash_file_str: &str, file_str: &str) -> Option<String> {
-        if let Ok(hash_file_res) = File::open(hash_file_str) {
-            let hash_file_reader = BufReader::new(hash_file_res);
-            for line in hash_file_reader.lines() {
-                if let Ok(l) = line {
-                    // Break the line between `<hash>:<file>`
-                    let sha_args: Vec<&str> = l.split(':').collect();
-
-                    // File matched
-                    if let Some(sha_arg_file) = sha_args.get(1) {
-                        if sha_arg_file != &file_str {
-                            continue;
-                        }
-                        if let Some(sha_arg_hash) = sha_args.get(0) {
-                            return Some(String::from(*sha_arg_hash));
-                        }
-                    }
+/// The name of the manifest file used by older versions
+pub const LEGACY_SHA_FILE: &str = ".files.sha";
+
+/// The current version of the manifest format
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+/// The state of a single rendered page
+pub struct PageEntry {
+    /// The path of the markdown file
+    #[serde(with = "serde_path")]
+    pub source: PathBuf,
+    /// The path of the HTML file relative to the output directory
+    #[serde(with = "serde_path")]
+    pub output: PathBuf,
+    /// The SHA-256 hash of the markdown file content
+    pub hash: String,
+    /// The hash of the content and all other inputs of the rendered HTML file
+    #[serde(default)]
+    pub cache_key: String,
+    /// The size of the markdown file in bytes
+    pub size: u64,
+    /// The time the page was rendered, in RFC 3339 format
+    pub rendered: String,
+    /// The normalized markdown files this page depends on, e.g. by linking to them
+    #[serde(default, with = "serde_path::vec")]
+    pub dependencies: Vec<PathBuf>,
+    /// The title of the page
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    /// The description of the front matter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The page is a draft or hidden and not listed
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hidden: bool,
+    /// The time the page was first built, in RFC 3339 format
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub created: String,
+    /// The time the content of the page last changed, in RFC 3339 format
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub changed: String,
+    /// The files of the diagrams within the diagram cache used by the page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagrams: Vec<String>,
+}
+
+/// Returns true if `value` is false, to skip serializing default flags
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+/// The state of the last build
+pub struct Manifest {
+    /// The version of the manifest format
+    pub version: u32,
+    /// All pages of the last build
+    pub pages: Vec<PageEntry>,
+    /// All static assets of the last build, `rendered` is the time they were copied
+    #[serde(default)]
+    pub assets: Vec<PageEntry>,
+    /// All other files generated into the output directory, like the theme files and the feeds
+    #[serde(default, with = "serde_path::vec")]
+    pub generated: Vec<PathBuf>,
+    /// The index of every page within `pages`, keyed by its markdown file
+    #[serde(skip)]
+    index: HashMap<PathBuf, usize>,
+    /// The index of every asset within `assets`, keyed by its source file
+    #[serde(skip)]
+    asset_index: HashMap<PathBuf, usize>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            pages: Vec::new(),
+            assets: Vec::new(),
+            generated: Vec::new(),
+            index: HashMap::new(),
+            asset_index: HashMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Loads the manifest from `manifest_file`, migrating a legacy `.files.sha` next to it. A
+    /// missing or unreadable manifest results in an empty one, which forces a full rebuild.
+    pub fn load<P: AsRef<Path>>(manifest_file: P) -> Self {
+        let mut manifest = Manifest::read(manifest_file);
+        manifest.reindex();
+        manifest
+    }
+
+    fn read<P: AsRef<Path>>(manifest_file: P) -> Self {
+        let manifest_path = manifest_file.as_ref();
+        if !manifest_path.exists() {
+            let legacy_path = manifest_path.with_file_name(LEGACY_SHA_FILE);
+            return if legacy_path.exists() {
+                info!("Migrating legacy hash file '{}'", legacy_path.display());
+                Manifest::from_legacy(&legacy_path)
+            } else {
+                Manifest::default()
+            };
+        }
+
+        let manifest = File::open(manifest_path)
+            .map_err(Error::from)
+            .and_then(|file| serde_json::from_reader::<_, Manifest>(BufReader::new(file))
+                .map_err(|e| Error::from(e.to_string())));
+        match manifest {
+            Ok(ref manifest) if manifest.version > MANIFEST_VERSION => {
+                warn!("Manifest '{}' has the unknown version {}, rebuilding everything.",
+                      manifest_path.display(), manifest.version);
+                Manifest::default()
+            }
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("Unable to read manifest '{}', rebuilding everything: {}",
+                      manifest_path.display(), e);
+                Manifest::default()
+            }
+        }
+    }
+
+    /// Reads a legacy hash file with lines in form `<hash>:<file>` or `<hash>:<file>:<output>`
+    fn from_legacy(legacy_path: &Path) -> Self {
+        let mut manifest = Manifest::default();
+        if let Ok(legacy_file) = File::open(legacy_path) {
+            for line in BufReader::new(legacy_file).lines().map_while(|line| line.ok()) {
+                let mut parts = line.splitn(3, ':');
+                if let (Some(hash), Some(source)) = (parts.next(), parts.next()) {
+                    manifest.pages.push(PageEntry {
+                        source: PathBuf::from(source),
+                        output: parts.next().map(PathBuf::from).unwrap_or_default(),
+                        hash: hash.to_owned(),
+                        ..PageEntry::default()
+                    });
                 }
             }
         }
-        None
+        manifest
     }
 
-    /// Writes all input files and their hashes into the file `hash_file_str`
-    pub fn write_file_hash(input_paths: &mut Vec<InputPaths>, hash_file_str: &str) -> Result<()> {
-        // Renew the hash_file
-        if Path::new(hash_file_str).exists() {
-            fs::remove_file(hash_file_str)?;
+    /// Writes the manifest to `manifest_file` and removes a legacy hash file next to it
+    pub fn save<P: AsRef<Path>>(&self, manifest_file: P) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Unable to serialize the manifest: {}", e))?;
+        File::create(&manifest_file)?.write_all(content.as_bytes())?;
+
+        let legacy_path = manifest_file.as_ref().with_file_name(LEGACY_SHA_FILE);
+        if legacy_path.exists() {
+            fs::remove_file(legacy_path)?;
         }
-        let mut hash_file = File::create(hash_file_str)?;
+        Ok(())
+    }
 
-        // Write content into file in form `<hash>:<file>`
-        let mut hash_file_content = String::new();
+    /// The stored state of the page with the markdown file `source`
+    pub fn page(&self, source: &Path) -> Option<&PageEntry> {
+        self.index.get(source).map(|&i| &self.pages[i])
+    }
+
+    /// The stored state of the asset with the source file `source`
+    pub fn asset(&self, source: &Path) -> Option<&PageEntry> {
+        self.asset_index.get(source).map(|&i| &self.assets[i])
+    }
+
+    /// Rebuilds the lookup indexes after `pages` or `assets` changed
+    pub fn reindex(&mut self) {
+        self.index = self.pages.iter()
+            .enumerate()
+            .map(|(i, page)| (page.source.clone(), i))
+            .collect();
+        self.asset_index = self.assets.iter()
+            .enumerate()
+            .map(|(i, asset)| (asset.source.clone(), i))
+            .collect();
+    }
+}
+
+pub struct Filehash;
+
+impl Filehash {
+    /// Reads the outputs of all pages, assets and generated files stored in `manifest_file`
+    pub fn read_outputs<P: AsRef<Path>>(manifest_file: P) -> Vec<PathBuf> {
+        let manifest = Manifest::load(manifest_file);
+        manifest.pages
+            .into_iter()
+            .chain(manifest.assets)
+            .map(|page| page.output)
+            .chain(manifest.generated)
+            .filter(|output| !output.as_os_str().is_empty())
+            .collect()
+    }
+
+    /// Writes all input files and assets, their hashes and outputs into the manifest
+    /// `manifest_file`, together with the other `generated` files
+    pub fn write_file_hash<P: AsRef<Path>>(input_paths: &[InputPaths], assets: &[Asset],
+                                           generated: &[PathBuf], manifest_file: P)
+                                           -> Result<()> {
+        let mut manifest = Manifest::default();
         for input_path in input_paths {
-            hash_file_content.push_str(format!("{}:{}\n",
-                                               input_path.hash.as_str(),
-                                               input_path.path.to_str()
-                                               .ok_or_else(|| "Unable to stringfy input path.")?)
-                                       .as_str());
+            manifest.pages.push(PageEntry {
+                source: input_path.path.clone(),
+                output: input_path.output.clone(),
+                hash: input_path.hash.clone(),
+                cache_key: input_path.cache_key.clone(),
+                size: input_path.size,
+                rendered: input_path.rendered.clone(),
+                dependencies: input_path.dependencies.clone(),
+                title: input_path.title(&input_path.front_matter),
+                description: input_path.front_matter.description.clone(),
+                hidden: !input_path.front_matter.is_listed(),
+                created: input_path.created.clone(),
+                changed: input_path.changed.clone(),
+                diagrams: input_path.diagrams.clone(),
+            });
         }
-        hash_file.write_all(hash_file_content.as_bytes())?;
-
-        Ok (())
+        for asset in assets {
+            manifest.assets.push(PageEntry {
+                source: asset.path.clone(),
+                output: asset.output.clone(),
+                hash: asset.hash.clone(),
+                size: asset.size,
+                rendered: asset.copied.clone(),
+                ..PageEntry::default()
+            });
+        }
+        manifest.pages.sort_by(|a, b| a.source.cmp(&b.source));
+        manifest.assets.sort_by(|a, b| a.source.cmp(&b.source));
+        manifest.generated = generated.to_vec();
+        manifest.generated.sort();
+        manifest.save(manifest_file)
     }
 
-    /// Calculate the hash of the given `file_str`
-    fn get_file_hash(file_str: &str) -> Result<(String)> {
-        let mut buffer = String::new();
-        let mut file_instance = File::open(file_str)?;
+    /// Calculate the hex encoded SHA-256 hash of `content`
+    pub fn hash(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
 
-        file_instance.read_to_string(&mut buffer)?;
+    /// The current time in RFC 3339 format, used as render timestamp
+    pub fn timestamp() -> String {
+        Utc::now().to_rfc3339()
+    }
 
-        let file_uuid = Uuid::new_v5(&NAMESPACE_DNS, buffer.as_str());
-        debug!("Calculated file hash: {}", file_uuid);
+    /// The cache key of a page with the content `hash`, rendered with all other inputs
+    /// hashed into `fingerprint`
+    pub fn cache_key(hash: &str, fingerprint: &str) -> String {
+        Filehash::hash(format!("{}\0{}", hash, fingerprint).as_bytes())
+    }
 
-        Ok(file_uuid.to_string())
+    /// Checks whether the page `file` with the already read `content` is up to date: its
+    /// content and all other inputs hashed into `fingerprint` are unchanged since the build
+    /// stored in `manifest`, and its `output_file` still exists. Returns the stored page on
+    /// success and the current hash as error otherwise.
+    pub fn check_cache_currency(manifest: &Manifest, file: &Path, content: &[u8],
+                                fingerprint: &str, output_file: &Path) -> Result<PageEntry> {
+        let page = Filehash::check_hash_currency(manifest, file, content)?;
+        if page.cache_key != Filehash::cache_key(&page.hash, fingerprint) {
+            debug!("Layout, renderer or version changed for '{}'", file.display());
+            Err(Error::from(page.hash))
+        } else if !output_file.exists() {
+            debug!("Output '{}' is missing", output_file.display());
+            Err(Error::from(page.hash))
+        } else {
+            Ok(page)
+        }
     }
 
-    /// Checks whether the calculated hash of `file_str` is equal to the hash stored
-    /// in the file `hash_file_str`
-    pub fn check_hash_currency(hash_file_str: &str, file_str: &str) -> Result<String> {
-        debug!("Check hash currency of '{}'", file_str);
-        let current_file_hash = Filehash::get_file_hash(file_str)?;
-        match Filehash::read_file_hash(hash_file_str, file_str) {
-            Some(stored_file_hash) => {
+    /// Checks whether the hash of the already read `content` of `file` is equal to the hash
+    /// stored in `manifest`. Returns the stored page on success and the current hash as error
+    /// otherwise.
+    pub fn check_hash_currency(manifest: &Manifest, file: &Path, content: &[u8])
+                               -> Result<PageEntry> {
+        debug!("Check hash currency of '{}'", file.display());
+        let current_file_hash = Filehash::hash(content);
+        debug!("Calculated file hash: {}", current_file_hash);
+        match manifest.page(file) {
+            Some(stored_page) => {
                 // Stored file hash was found
-                debug!("Extracted file hash:  {}", stored_file_hash);
+                debug!("Extracted file hash:  {}", stored_page.hash);
 
                 // Calculated hash of current file equals stored hash?
-                if current_file_hash != stored_file_hash {
+                if current_file_hash != stored_page.hash {
                     Err(Error::from(current_file_hash))
                 } else {
-                    Ok(current_file_hash)
+                    Ok(stored_page.clone())
                 }
             },
             None => {
@@ -0,0 +1,110 @@
+//! Everything related to the front matter of pages
+//!
+//! Markdown pages may start with a YAML block enclosed by `---` lines, which sets metadata like
+//! the title of the page or its position within the site menu. The block is removed before the
+//! page is rendered.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use yaml_rust2::{Yaml, YamlLoader};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The metadata of a page
+pub struct FrontMatter {
+    /// The title of the page, replacing the file name
+    pub title: Option<String>,
+    /// The menu the page is listed in, like `main`
+    pub menu: Option<String>,
+    /// The position within the menu, lower weights come first
+    pub weight: i64,
+    /// The title of the menu entry the page is nested below
+    pub parent: Option<String>,
+    /// The page is work in progress, rendered for previews but not listed anywhere
+    pub draft: bool,
+    /// The page is rendered but not listed in the sitemap or feeds
+    pub hidden: bool,
+    /// The date of the last change, replacing the modification time of the file
+    pub date: Option<DateTime<Utc>>,
+    /// A short summary of the page, used by the feeds
+    pub description: Option<String>,
+}
+
+impl FrontMatter {
+    /// Returns true if the page should be listed, which means it is neither a draft nor hidden
+    pub fn is_listed(&self) -> bool {
+        !self.draft && !self.hidden
+    }
+}
+
+/// Splits `content` into its front matter and the remaining page content. Pages without front
+/// matter keep their whole content.
+pub fn split(content: &str) -> Result<(FrontMatter, &str), String> {
+    let (yaml, body) = match find_block(content) {
+        Some(block) => block,
+        None => return Ok((FrontMatter::default(), content)),
+    };
+
+    let documents = YamlLoader::load_from_str(yaml).map_err(|e| e.to_string())?;
+    let document = match documents.into_iter().next() {
+        Some(Yaml::Hash(document)) => Yaml::Hash(document),
+        None | Some(Yaml::Null) => return Ok((FrontMatter::default(), body)),
+        Some(_) => return Err("The front matter is no mapping".to_owned()),
+    };
+
+    let string = |key: &str| -> Result<Option<String>, String> {
+        match document[key] {
+            Yaml::BadValue | Yaml::Null => Ok(None),
+            Yaml::String(ref value) => Ok(Some(value.clone())),
+            Yaml::Integer(value) => Ok(Some(value.to_string())),
+            Yaml::Real(ref value) => Ok(Some(value.clone())),
+            _ => Err(format!("'{}' must be a string", key)),
+        }
+    };
+    let boolean = |key: &str| match document[key] {
+        Yaml::BadValue | Yaml::Null => Ok(false),
+        Yaml::Boolean(value) => Ok(value),
+        _ => Err(format!("'{}' must be true or false", key)),
+    };
+    let front_matter = FrontMatter {
+        title: string("title")?,
+        menu: string("menu")?,
+        weight: match document["weight"] {
+            Yaml::BadValue | Yaml::Null => 0,
+            Yaml::Integer(weight) => weight,
+            _ => return Err("'weight' must be an integer".to_owned()),
+        },
+        parent: string("parent")?,
+        draft: boolean("draft")?,
+        hidden: boolean("hidden")?,
+        date: match string("date")? {
+            Some(date) => Some(parse_date(&date)?),
+            None => None,
+        },
+        description: string("description")?,
+    };
+    Ok((front_matter, body))
+}
+
+/// Parses a date like `2017-05-04` or `2017-05-04T19:00:00+02:00`
+fn parse_date(date: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        })
+        .map_err(|_| format!("'{}' is no date like 2017-05-04 or 2017-05-04T19:00:00Z", date))
+}
+
+/// Finds the YAML block at the start of `content`, returning it and the content after it
+fn find_block(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
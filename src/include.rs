@@ -0,0 +1,345 @@
+//! Everything related to including pages into other pages
+//!
+//! A line like `{{#include snippets/location.md}}` is replaced by the content of that page, a line
+//! like `{{#include rules.md#code_of_conduct}}` by a single section of it, which is selected by
+//! the anchor of its heading. Included headings are shifted below the heading the include appears
+//! in and relative links are rebased onto the including page. Includes may be nested, cycles are
+//! reported instead of expanded. Every transitively included page is a dependency of the
+//! including page, so changing it re-renders the page.
+
+use dependency::normalize;
+use encoding::{path_from_link, url_path};
+use frontmatter;
+use renderer::{escape_html, Placeholders};
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The start of an include directive
+const DIRECTIVE: &str = "{{#include ";
+
+/// The markdown content of a page with all includes expanded
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Expanded {
+    /// The expanded markdown, failed includes are replaced by placeholders
+    pub content: String,
+    /// All transitively included pages, also the ones which could not be included
+    pub includes: Vec<PathBuf>,
+    /// The errors of the failed includes
+    pub errors: Vec<String>,
+    /// The placeholders of the failed includes
+    pub placeholders: Placeholders,
+}
+
+/// Expands all includes within the markdown `content` of the page `source`. Only pages with one
+/// of the source `extensions` can be included.
+pub fn expand(source: &Path, content: &str, extensions: &[String]) -> Expanded {
+    let mut expander = Expander {
+        extensions,
+        stack: vec![normalize(source)],
+        includes: Vec::new(),
+        errors: Vec::new(),
+        placeholders: Placeholders::new("include", content),
+    };
+    let content = expander.expand(source, content);
+    Expanded {
+        content,
+        includes: expander.includes,
+        errors: expander.errors,
+        placeholders: expander.placeholders,
+    }
+}
+
+impl Expanded {
+    /// Replaces the placeholders of failed includes within the rendered `html` by their errors
+    pub fn insert_errors(&self, html: &str) -> String {
+        let mut html = html.to_owned();
+        for (index, error) in self.errors.iter().enumerate() {
+            let marker = format!("<p class=\"include-error\">{}</p>", escape_html(error));
+            html = self.placeholders.replace(&html, index, &marker, true);
+        }
+        html
+    }
+}
+
+/// Expands includes recursively, keeping track of the pages being expanded
+struct Expander<'a> {
+    extensions: &'a [String],
+    stack: Vec<PathBuf>,
+    includes: Vec<PathBuf>,
+    errors: Vec<String>,
+    placeholders: Placeholders,
+}
+
+impl<'a> Expander<'a> {
+    /// Replaces all include directives within the `content` of `source`
+    fn expand(&mut self, source: &Path, content: &str) -> String {
+        let mut result = String::with_capacity(content.len());
+        let mut level = 0;
+        for (line, code) in classify(content) {
+            if !code {
+                if let Some(heading) = heading_level(line) {
+                    level = heading;
+                }
+                if let Some(target) = directive(line) {
+                    let included = self.include(source, target, level).unwrap_or_else(|error| {
+                        self.errors.push(error);
+                        self.placeholders.get(self.errors.len() - 1)
+                    });
+                    // Blank lines keep the included blocks apart from the surrounding ones
+                    result.push('\n');
+                    result.push_str(included.trim_end_matches('\n'));
+                    result.push_str("\n\n");
+                    continue;
+                }
+            }
+            result.push_str(line);
+        }
+        result
+    }
+
+    /// The expanded content of the include `target` of `source`, shifted below the heading of
+    /// `level`
+    fn include(&mut self, source: &Path, target: &str, level: usize) -> Result<String, String> {
+        let (link, section) = match target.find('#') {
+            Some(anchor) => (&target[..anchor], Some(&target[anchor + 1..])),
+            None => (target, None),
+        };
+        let base = normalize(source.parent().unwrap_or_else(|| Path::new("")));
+        let path = normalize(&base.join(path_from_link(link)));
+        let is_page = path.extension()
+                          .and_then(|extension| extension.to_str())
+                          .is_some_and(|extension| self.extensions.iter().any(|e| e == extension));
+        if link.is_empty() || link.starts_with('/') || link.contains(':') || !is_page {
+            return Err(format!("Only relative pages can be included, not '{}'", target));
+        }
+        if !self.includes.contains(&path) {
+            self.includes.push(path.clone());
+        }
+        if self.stack.contains(&path) {
+            let cycle: Vec<String> = self.stack.iter()
+                                               .chain(Some(&path))
+                                               .map(|page| page.display().to_string())
+                                               .collect();
+            return Err(format!("Include cycle {}", cycle.join(" -> ")));
+        }
+
+        let buffer = fs::read_to_string(&path)
+            .map_err(|e| format!("Unable to include '{}': {}", path.display(), e))?;
+        let (_, body) = frontmatter::split(&buffer)
+            .map_err(|e| format!("Invalid front matter of '{}': {}", path.display(), e))?;
+        self.stack.push(path.clone());
+        let content = self.expand(&path, body);
+        self.stack.pop();
+
+        let content = match section {
+            Some(anchor) => select_section(&content, anchor).ok_or_else(|| {
+                format!("No section '{}' within '{}'", anchor, path.display())
+            })?,
+            None => content,
+        };
+        let included_base = path.parent().unwrap_or_else(|| Path::new(""));
+        Ok(rebase_links(&shift_headings(&content, level), &relative(included_base, &base)))
+    }
+}
+
+/// The target of an include directive on `line`, like `rules.md#code_of_conduct`
+fn directive(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    if line.len() - line.trim_start().len() >= 4 {
+        return None;
+    }
+    let target = trimmed.strip_prefix(DIRECTIVE)?.strip_suffix("}}")?.trim();
+    if target.is_empty() { None } else { Some(target) }
+}
+
+/// Splits `content` into lines, telling whether each is part of a code block
+fn classify(content: &str) -> Vec<(&str, bool)> {
+    let mut lines = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut previous_blank = true;
+    let mut indented_code = false;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start_matches(' ');
+        let is_fence = line.len() - trimmed.len() < 4 &&
+                       (trimmed.starts_with("```") || trimmed.starts_with("~~~"));
+        if let Some(marker) = fence {
+            if is_fence && trimmed.starts_with(marker) {
+                fence = None;
+            }
+            lines.push((line, true));
+            continue;
+        }
+        if is_fence {
+            fence = Some(&trimmed[..3]);
+            lines.push((line, true));
+            previous_blank = false;
+            continue;
+        }
+        if line.trim().is_empty() {
+            previous_blank = true;
+            lines.push((line, indented_code));
+            continue;
+        }
+        let indented = line.starts_with("    ") || line.starts_with('\t');
+        indented_code = indented && (previous_blank || indented_code);
+        previous_blank = false;
+        lines.push((line, indented_code));
+    }
+    lines
+}
+
+/// The level of the heading on `line`, if it is one written with `#`
+fn heading_level(line: &str) -> Option<usize> {
+    if line.starts_with("    ") || line.starts_with('\t') {
+        return None;
+    }
+    let trimmed = line.trim_start();
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    let rest = &trimmed[level..];
+    if (1..=6).contains(&level) && (rest.trim().is_empty() || rest.starts_with([' ', '\t'])) {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+/// The anchor of a heading text or an include section, ignoring case, markup and whether words
+/// are separated by spaces, `-` or `_`
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_matches('_').to_owned()
+}
+
+/// The section of `content` whose heading matches `anchor`, up to the next heading of the same
+/// or a higher level
+fn select_section(content: &str, anchor: &str) -> Option<String> {
+    let anchor = slug(anchor);
+    let mut section: Option<(usize, String)> = None;
+    for (line, code) in classify(content) {
+        let level = if code { None } else { heading_level(line) };
+        match (section.as_mut(), level) {
+            (Some(&mut (start, _)), Some(level)) if level <= start => break,
+            (Some(&mut (_, ref mut text)), _) => text.push_str(line),
+            (None, Some(level)) => {
+                let title = line.trim().trim_matches('#');
+                if slug(title) == anchor {
+                    section = Some((level, line.to_owned()));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    section.map(|(_, text)| text)
+}
+
+/// Shifts the headings of `content`, so the highest one is one level below `level`. Content
+/// included outside of any heading keeps its levels.
+fn shift_headings(content: &str, level: usize) -> String {
+    let lines = classify(content);
+    let highest = lines.iter()
+                       .filter(|&&(_, code)| !code)
+                       .filter_map(|&(line, _)| heading_level(line))
+                       .min();
+    let shift = match highest {
+        Some(highest) if level > 0 => (level + 1) as isize - highest as isize,
+        _ => 0,
+    };
+    if shift == 0 {
+        return content.to_owned();
+    }
+
+    let mut result = String::with_capacity(content.len());
+    for (line, code) in lines {
+        match heading_level(line).filter(|_| !code) {
+            Some(heading) => {
+                let shifted = (heading as isize + shift).clamp(1, 6) as usize;
+                let rest = line.trim_start()[heading..].to_owned();
+                result.push_str(&"#".repeat(shifted));
+                result.push_str(&rest);
+            }
+            None => result.push_str(line),
+        }
+    }
+    result
+}
+
+/// The relative path from the directory `to` to the directory `from`, both lexically normalized
+fn relative(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|&(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..to.len() {
+        path.push("..");
+    }
+    for component in &from[common..] {
+        path.push(component.as_os_str());
+    }
+    path
+}
+
+/// Prefixes all relative link targets within the markdown `content` with `prefix`, so links of
+/// an included page keep pointing to the same files
+fn rebase_links(content: &str, prefix: &Path) -> String {
+    if prefix.as_os_str().is_empty() {
+        return content.to_owned();
+    }
+    let mut result = String::with_capacity(content.len());
+    for (line, code) in classify(content) {
+        if code {
+            result.push_str(line);
+            continue;
+        }
+
+        // Reference definitions like `[label]: target "title"`
+        let trimmed = line.trim_start();
+        if let Some(end) = trimmed.find("]:").filter(|_| trimmed.starts_with('[')) {
+            let definition = trimmed[end + 2..].trim_start();
+            let (opening, target, rest) = split_target(definition);
+            result.push_str(&line[..line.len() - definition.len()]);
+            result.push_str(opening);
+            result.push_str(&rebase(target, prefix));
+            result.push_str(rest);
+            continue;
+        }
+
+        // Inline links and images like `[text](target "title")`
+        let mut rest = line;
+        while let Some(start) = rest.find("](") {
+            result.push_str(&rest[..start + 2]);
+            let (opening, target, after) = split_target(&rest[start + 2..]);
+            result.push_str(opening);
+            result.push_str(&rebase(target, prefix));
+            rest = after;
+        }
+        result.push_str(rest);
+    }
+    result
+}
+
+/// Splits the link target at the start of `text` from an opening `<` and the text after it
+fn split_target(text: &str) -> (&str, &str, &str) {
+    let opening = if text.starts_with('<') { 1 } else { 0 };
+    let end = text[opening..].find(|c: char| c.is_whitespace() || c == ')' || c == '>')
+                             .map_or(text.len(), |end| end + opening);
+    (&text[..opening], &text[opening..end], &text[end..])
+}
+
+/// Rebases a single link `target` onto `prefix`, keeping anchors and queries. Absolute targets,
+/// URLs and anchors within the page are kept.
+fn rebase(target: &str, prefix: &Path) -> String {
+    let split = target.find(['#', '?']).unwrap_or(target.len());
+    let (path, suffix) = target.split_at(split);
+    if path.is_empty() || path.starts_with('/') || path.contains(':') {
+        return target.to_owned();
+    }
+    format!("{}{}", url_path(&normalize(&prefix.join(path_from_link(path)))), suffix)
+}
@@ -6,6 +6,7 @@
 #[macro_use]
 extern crate log;
 extern crate glob;
+extern crate hyper;
 extern crate iron;
 extern crate markdown;
 extern crate mowl;
@@ -13,16 +14,52 @@ extern crate mowl;
 extern crate error_chain;
 #[macro_use]
 extern crate lazy_static;
-extern crate uuid;
 extern crate rayon;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+extern crate serde_json;
+extern crate sha2;
+extern crate chrono;
+extern crate ignore;
+extern crate percent_encoding;
+extern crate wait_timeout;
+extern crate yaml_rust2;
 
+pub mod breadcrumbs;
+pub mod config;
+pub mod dependency;
+pub mod diagram;
+pub mod discovery;
+pub mod encoding;
 pub mod error;
+pub mod feed;
 pub mod filehash;
+pub mod frontmatter;
+pub mod include;
+pub mod math;
+pub mod menu;
+pub mod recent;
+pub mod renderer;
+pub mod report;
+pub mod server;
+pub mod sitemap;
+pub mod template;
 
+use config::{Config, CONFIG_FILE};
+use diagram::{Diagrams, DIAGRAM_CACHE};
 use error::*;
-use glob::glob;
+use breadcrumbs::Breadcrumbs;
+use feed::{Feed, FeedEntry, ATOM_FILE, JSON_FILE, RSS_FILE};
+use frontmatter::FrontMatter;
+use include::Expanded;
+use menu::{Menu, MAIN_MENU};
+use recent::RECENT_FILE;
+use sitemap::{SitemapEntry, ROBOTS_FILE, SITEMAP_FILE};
+use template::Theme;
+use chrono::{DateTime, Utc};
 use log::LogLevel;
-use markdown::to_html;
 
 use iron::prelude::*;
 use iron::status;
@@ -30,88 +67,339 @@ use iron::headers::ContentType;
 use iron::mime::Mime;
 
 
-use std::fs::{self, canonicalize, create_dir_all, File, OpenOptions};
-use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, canonicalize, create_dir_all, File};
+use std::path::{Component, Path, PathBuf};
 use std::io::prelude::*;
 use std::str;
-use filehash::Filehash;
-use rayon::iter::{ParallelIterator, IntoParallelRefMutIterator};
+use std::sync::atomic::{AtomicBool, Ordering};
+use filehash::{Filehash, Manifest, MANIFEST_FILE};
+use renderer::{escape_html, Options, Rendered, Renderer};
+use report::{BuildReport, PageReport, PageStatus};
+use server::Binding;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+                  IntoParallelRefMutIterator, ParallelIterator};
+
+/// The listing of all pages, generated unless the input has an index page
+pub const INDEX_FILE: &str = "index.html";
 
 pub struct InputPaths {
     path: PathBuf,
     hash: String,
+    cache_key: String,
+    output: PathBuf,
+    size: u64,
+    rendered: String,
+    created: String,
+    changed: String,
+    dependencies: Vec<PathBuf>,
+    diagrams: Vec<String>,
+    warnings: Vec<String>,
+    content: Option<String>,
+    front_matter: FrontMatter,
+    up_to_date: bool,
+    renderer: Renderer,
+    root: PathBuf,
+    prefix: PathBuf,
+}
+
+/// A static asset, which is copied into the output unchanged
+pub struct Asset {
+    path: PathBuf,
+    output: PathBuf,
+    hash: String,
+    size: u64,
+    copied: String,
+}
+
+/// Everything needed to render a single page
+struct RenderContext<'a> {
+    /// The directory where the HTML output is generated
+    output_dir: &'a Path,
+    /// The manifest of the last build
+    manifest: &'a Manifest,
+    /// The theme every page is rendered with
+    theme: &'a Theme,
+    /// The site menu shown on every page
+    menu: &'a Menu,
+    /// The section titles of the breadcrumbs
+    breadcrumbs: &'a Breadcrumbs,
+    /// The hash of all inputs besides the page content which influence the output
+    fingerprint: &'a str,
+    /// Render all pages, regardless whether they are up to date
+    force: bool,
+    /// The settings of the renderers
+    options: Options<'a>,
 }
 
 impl InputPaths {
-    fn new(path: &str) -> Self {
+    fn new(path: PathBuf, renderer: Renderer, root: &Path, prefix: &Path) -> Self {
         InputPaths {
-            path: PathBuf::from(path),
+            path,
             hash: String::new(),
+            cache_key: String::new(),
+            output: PathBuf::new(),
+            size: 0,
+            rendered: String::new(),
+            created: String::new(),
+            changed: String::new(),
+            dependencies: Vec::new(),
+            diagrams: Vec::new(),
+            warnings: Vec::new(),
+            content: None,
+            front_matter: FrontMatter::default(),
+            up_to_date: false,
+            renderer,
+            root: root.to_path_buf(),
+            prefix: prefix.to_path_buf(),
         }
     }
 
-    fn parse_as_html(&mut self,
-                     input_root_dir: &str,
-                     output_dir: &str,
-                     sha_file: &str) -> Result<PathBuf> {
+    /// Reads the markdown content of the file
+    fn read(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        File::open(&self.path)
+            .and_then(|mut f| f.read_to_end(&mut buffer))
+            .chain_err(|| ErrorKind::FileOperation("read".to_owned(), self.path.clone()))?;
+        String::from_utf8(buffer).map_err(|e| {
+            let (line, column) = line_column(e.as_bytes(), e.utf8_error().valid_up_to());
+            ErrorKind::InvalidUtf8(self.path.clone(), line, column).into()
+        })
+    }
 
-        // Open the file and read its content
-        let mut f = File::open(&self.path)?;
-        let mut buffer = String::new();
-        f.read_to_string(&mut buffer)?;
+    /// Splits the source `buffer` into the front matter and the content to render. Only
+    /// markdown pages have front matter.
+    fn split_front_matter<'b>(&self, buffer: &'b str) -> Result<(FrontMatter, &'b str)> {
+        match self.renderer {
+            Renderer::Markdown => frontmatter::split(buffer).map_err(|message| {
+                ErrorKind::InvalidFrontMatter(self.path.clone(), message).into()
+            }),
+            Renderer::Text => Ok((FrontMatter::default(), buffer)),
+        }
+    }
 
-        // Creating the related HTML file in output_directory
-        match self.path.to_str() {
-            Some(file_str) => {
-                // Get canonical normal forms of the input path and the recursively
-                // searched directories
-                let file_buf_n = canonicalize(&PathBuf::from(file_str))?;
-                let file_str_n = file_buf_n.to_str()
-                                    .ok_or_else(|| "Unable to stringify canonical normal form of md-file.")?;
-                let input_root_buf_n = canonicalize(&PathBuf::from(input_root_dir))?;
-                let mut input_root_str_n = String::from(
-                    input_root_buf_n.to_str()
-                    .ok_or_else(|| "Unable to stringify canonical normal form of input root.")?
-                );
-
-                // Add native seperator to avoid getting the wrong path
-                input_root_str_n.push(MAIN_SEPARATOR);
-
-                // Reduce the input dir and replace the extension
-                let output_str = String::from(file_str_n)
-                    .replace(input_root_str_n.as_str(), "")
-                    .replace(".md", ".html");
-                let output_path = Path::new(output_str.as_str());
-
-                match output_path.parent() {
-                    Some(parent) => {
-                        // Creating folder structure if neccessary
-                        let parent_path = Path::new(output_dir)
-                            .join(parent.to_str().unwrap_or("."));
-                        create_dir_all(parent_path)?;
-                    },
-                    None => bail!("Can't get output path parent."),
-                }
+    /// The title of the page: the one of the front matter or the file name
+    fn title(&self, front_matter: &FrontMatter) -> String {
+        front_matter.title.clone().unwrap_or_else(|| {
+            self.path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
 
-                match Filehash::check_hash_currency(sha_file, file_str) {
-                    Ok(hash) => {
-                        // File hash is up to date, no need to rebuild
-                        self.hash = hash;
-                        debug!("File '{}' hash up to date.", file_str);
-                    },
-                    Err(hash) => {
-                        // Creating the ouput HTML file
-                        self.hash = hash.to_string();
-                        info!("Parsing file: {}", file_str);
-                        let output_file_path = PathBuf::from(&output_dir)
-                                                    .join(output_path);
-                        let mut output_file = File::create(&output_file_path)?;
-                        output_file.write_all(to_html(&buffer).as_bytes())?;
-                    },
+    /// The date of the last change: the one of the front matter or the modification time
+    fn last_modified(&self) -> DateTime<Utc> {
+        self.front_matter.date.unwrap_or_else(|| {
+            fs::metadata(&self.path)
+                .and_then(|metadata| metadata.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now())
+        })
+    }
+
+    /// Renders the source `buffer` into the layout of the `theme`, together with the `menu` and
+    /// the `breadcrumbs`. Returns the whole page, together with the warnings of the renderer and
+    /// the includes, and the pages the page depends on.
+    fn render(&self, buffer: &str, theme: &Theme, menu: &Menu, breadcrumbs: &Breadcrumbs,
+              options: &Options) -> Result<(Rendered, Vec<PathBuf>)> {
+        let (front_matter, content) = self.split_front_matter(buffer)?;
+        let title = self.title(&front_matter);
+        let expanded = match self.renderer {
+            Renderer::Markdown => include::expand(&self.path, content, options.extensions),
+            Renderer::Text => Expanded { content: content.to_owned(), ..Expanded::default() },
+        };
+        let mut rendered = self.renderer.render(&expanded.content, options);
+        let mut warnings = expanded.errors.clone();
+        warnings.append(&mut rendered.warnings);
+        rendered.warnings = warnings;
+        rendered.html = theme.render_page(&title,
+                                          &expanded.insert_errors(&rendered.html),
+                                          &self.output,
+                                          &[("menu", &menu.render(&self.output)),
+                                            ("breadcrumbs",
+                                             &breadcrumbs.render(&self.output, &title))]);
+
+        // Included pages are dependencies as well, even nested ones
+        let mut dependencies = match self.renderer {
+            Renderer::Markdown => dependency::extract_links(&self.path, buffer, options.extensions),
+            Renderer::Text => Vec::new(),
+        };
+        for include in expanded.includes {
+            if !dependencies.contains(&include) {
+                dependencies.push(include);
+            }
+        }
+        Ok((rendered, dependencies))
+    }
+
+    /// The path of the HTML output file relative to the output directory, replacing only the
+    /// final extension
+    fn output_path(&self) -> Result<PathBuf> {
+        let relative_path = discovery::relative_path(&self.path, &self.root)?;
+        Ok(self.prefix.join(relative_path.with_extension("html")))
+    }
+
+
+    /// Creates the report of this page for the given `status` and `error`. Links to pages
+    /// which are not within the `current` pages are reported as warnings.
+    fn report(&mut self, status: PageStatus, error: Option<Error>, current: &HashSet<PathBuf>)
+              -> PageReport {
+        if status == PageStatus::Failed || status == PageStatus::Skipped {
+            // Neither rendered nor verified in this run, so force a rebuild of the page next time
+            self.cache_key.clear();
+        }
+
+        let mut warnings = Vec::new();
+        if status != PageStatus::Failed && status != PageStatus::Skipped {
+            if self.size == 0 {
+                warnings.push("The page is empty".to_owned());
+            }
+            for dependency in &self.dependencies {
+                if !current.contains(dependency) {
+                    warnings.push(format!("Link to the missing page '{}'", dependency.display()));
                 }
-                return Ok(output_path.to_path_buf());
+            }
+            warnings.extend(self.warnings.iter().cloned());
+        }
+
+        PageReport {
+            source: self.path.clone(),
+            output: match status {
+                PageStatus::Rendered | PageStatus::UpToDate => Some(self.output.clone()),
+                _ => None,
+            },
+            status,
+            error: error.map(|e| full_message(&e)),
+            warnings,
+        }
+    }
+
+    /// Reads the file and its front matter
+    fn load(&mut self) -> Result<()> {
+        self.content = None;
+        self.warnings.clear();
+        self.front_matter = FrontMatter::default();
+
+        // Remember the output, even if reading fails below
+        self.output = self.output_path()?;
+
+        // Open the file and read its content
+        let buffer = self.read()?;
+        self.size = buffer.len() as u64;
+        self.front_matter = self.split_front_matter(&buffer)?.0;
+        self.content = Some(buffer);
+        Ok(())
+    }
+
+    /// Checks whether the loaded file is up to date with the last build
+    fn prepare(&mut self, context: &RenderContext) -> Result<()> {
+        let buffer = self.content.take().ok_or("The file could not be read.")?;
+
+        let output_file_path = context.output_dir.join(&self.output);
+        match Filehash::check_cache_currency(context.manifest, &self.path, buffer.as_bytes(),
+                                             context.fingerprint, &output_file_path) {
+            Ok(page) if !context.force => {
+                // The content did not change, so the stored dependencies are still valid
+                self.hash = page.hash;
+                self.cache_key = page.cache_key;
+                self.rendered = page.rendered;
+                self.dependencies = page.dependencies;
+                self.up_to_date = true;
+            },
+            current => {
+                self.hash = match current {
+                    Ok(page) => page.hash,
+                    Err(hash) => hash.to_string(),
+                };
+                // The dependencies are found while rendering the page
+                self.dependencies.clear();
+                self.up_to_date = false;
+            },
+        }
+
+        // Remember when the page appeared and when its content changed the last time, older
+        // manifests only know the render time
+        let now = Filehash::timestamp();
+        let since = |time: &str, fallback: &str| {
+            [time, fallback].iter().find(|time| !time.is_empty())
+                            .map_or_else(|| now.clone(), |time| time.to_string())
+        };
+        match context.manifest.page(&self.path) {
+            Some(stored) => {
+                // Pages not rendered again keep using their cached diagrams
+                self.diagrams = stored.diagrams.clone();
+                self.created = since(&stored.created, &stored.rendered);
+                self.changed = if stored.hash == self.hash {
+                    since(&stored.changed, &stored.rendered)
+                } else {
+                    now.clone()
+                };
+            },
+            None => {
+                self.diagrams.clear();
+                self.created = now.clone();
+                self.changed = now.clone();
+            },
+        }
+
+        self.content = Some(buffer);
+        Ok(())
+    }
+
+    /// Renders the prepared file into its HTML output, unless the file and all pages it depends
+    /// on are unchanged since the last build
+    fn parse_as_html(&mut self, context: &RenderContext, changed: &HashSet<PathBuf>)
+                     -> Result<PageStatus> {
+        let buffer = self.content.take().ok_or("The file could not be read.")?;
+
+        // Creating the related HTML file in output_directory
+        let file_str = self.path.display().to_string();
+        match self.output.parent() {
+            Some(parent) => {
+                // Creating folder structure if neccessary
+                let parent_path = context.output_dir.join(parent);
+                create_dir_all(&parent_path).chain_err(|| {
+                    ErrorKind::FileOperation("create the directory".to_owned(),
+                                             parent_path.clone())
+                })?;
             },
-            None => bail!("Can not stringfy file path"),
+            None => bail!("Can't get output path parent."),
+        }
+
+        if self.up_to_date && !dependency::is_affected(&self.dependencies, changed) {
+            // File hash is up to date, no need to rebuild
+            debug!("File '{}' hash up to date.", file_str);
+            Ok(PageStatus::UpToDate)
+        } else {
+            if self.up_to_date {
+                debug!("Page '{}' depends on changed pages.", file_str);
+            }
+
+            // Creating the ouput HTML file
+            self.cache_key = Filehash::cache_key(&self.hash, context.fingerprint);
+            self.rendered = Filehash::timestamp();
+            info!("Parsing file: {}", file_str);
+            let output_file_path = context.output_dir.join(&self.output);
+            // The dependencies are found again, since nested includes may have changed even if
+            // the page did not
+            let (Rendered { html, warnings, diagrams, diagram_errors }, dependencies) =
+                self.render(&buffer, context.theme, context.menu, context.breadcrumbs,
+                            &context.options)?;
+            self.dependencies = dependencies;
+            for warning in &warnings {
+                warn!("{}: {}", file_str, warning);
+            }
+            if diagram_errors {
+                // Render the missing diagrams next time, their tools may be available by then
+                self.cache_key.clear();
+            }
+            self.warnings = warnings;
+            self.diagrams = diagrams;
+            File::create(&output_file_path)
+                .and_then(|mut output_file| output_file.write_all(html.as_bytes()))
+                .chain_err(|| ErrorKind::FileOperation("write".to_owned(),
+                                                       output_file_path.clone()))?;
+            Ok(PageStatus::Rendered)
         }
     }
 }
@@ -128,7 +416,67 @@ lazy_static! {
     static ref AVI_MIME: Mime = "video/x-msvideo".parse::<Mime>().unwrap();
     static ref PNG_MIME: Mime = "image/png".parse::<Mime>().unwrap();
     static ref JPG_MIME: Mime = "image/jpeg".parse::<Mime>().unwrap();
-    static ref SHA_FILE: &'static str = ".files.sha";
+}
+
+impl Asset {
+    fn new(path: PathBuf, root: &Path, prefix: &Path) -> Result<Self> {
+        let output = prefix.join(discovery::relative_path(&path, root)?);
+        Ok(Asset {
+            path,
+            output,
+            hash: String::new(),
+            size: 0,
+            copied: String::new(),
+        })
+    }
+
+    /// Copies the asset into `output_dir`, unless its content is unchanged since the build
+    /// stored in `manifest` and the copy still exists
+    fn copy(&mut self, manifest: &Manifest, output_dir: &Path, force: bool) -> Result<PageStatus> {
+        let content = fs::read(&self.path)
+            .chain_err(|| ErrorKind::FileOperation("read".to_owned(), self.path.clone()))?;
+        self.size = content.len() as u64;
+        self.hash = Filehash::hash(&content);
+
+        let output_file_path = output_dir.join(&self.output);
+        if let Some(stored) = manifest.asset(&self.path) {
+            if !force && stored.hash == self.hash && output_file_path.is_file() {
+                debug!("Asset '{}' up to date.", self.path.display());
+                self.copied = stored.rendered.clone();
+                return Ok(PageStatus::UpToDate);
+            }
+        }
+
+        if let Some(parent) = output_file_path.parent() {
+            create_dir_all(parent).chain_err(|| {
+                ErrorKind::FileOperation("create the directory".to_owned(), parent.to_path_buf())
+            })?;
+        }
+        info!("Copying asset: {}", self.path.display());
+        fs::write(&output_file_path, &content)
+            .chain_err(|| ErrorKind::FileOperation("write".to_owned(), output_file_path.clone()))?;
+        self.copied = Filehash::timestamp();
+        Ok(PageStatus::Rendered)
+    }
+
+    /// Creates the report of this asset for the `result` of copying it
+    fn report(&mut self, result: Result<PageStatus>) -> PageReport {
+        let (status, error) = match result {
+            Ok(status) => (status, None),
+            Err(e) => {
+                // Force copying the asset next time
+                self.hash.clear();
+                (PageStatus::Failed, Some(full_message(&e)))
+            }
+        };
+        PageReport {
+            source: self.path.clone(),
+            output: if error.is_none() { Some(self.output.clone()) } else { None },
+            status,
+            error,
+            warnings: Vec::new(),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -138,6 +486,16 @@ pub struct Wiki {
     input_paths: Vec<InputPaths>,
     /// The html output paths
     output_paths: Vec<PathBuf>,
+    /// The configuration of the wiki
+    config: Config,
+    /// The input directory the pages were read from
+    input_directory: Option<PathBuf>,
+    /// The static assets of all input directories
+    assets: Vec<Asset>,
+    /// Render all pages, regardless whether they are up to date
+    force: bool,
+    /// Stop rendering at the first page which fails
+    fail_fast: bool,
 }
 
 impl Wiki {
@@ -146,6 +504,16 @@ impl Wiki {
         Self::default()
     }
 
+    /// Create a new `Wiki` instance using the given configuration
+    pub fn with_config(config: Config) -> Self {
+        Wiki { config, ..Self::default() }
+    }
+
+    /// The configuration of the wiki
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// Creates a new instance of the processing lib
     pub fn init_logging(&mut self, level: LogLevel) -> Result<()> {
         // Init logger crate
@@ -158,21 +526,54 @@ impl Wiki {
     }
     /// Reads all markdown files recursively from a given directory.
     /// Clears the current available input_paths
-    pub fn read_from_directory(&mut self, directory: &str) -> Result<()> {
+    pub fn read_from_directory<P: AsRef<Path>>(&mut self, directory: P) -> Result<()> {
+        let output_directory = self.config.output_directory.clone();
+        self.discover(directory.as_ref(), &output_directory)
+    }
+
+    /// Finds the pages and assets of `directory` and all mounted ones, skipping the files
+    /// generated into `output_directory`
+    fn discover(&mut self, directory: &Path, output_directory: &Path) -> Result<()> {
         /// Remove all input_paths
         self.input_paths.clear();
+        self.assets.clear();
+        self.input_directory = Some(directory.to_path_buf());
 
-        /// Gather new content
-        let md_path = PathBuf::from(&directory).join("**").join("*.md");
-        if !Path::new(&directory).is_dir() {
-            bail!("The path '{}' does not exist", directory);
+        // Gather new content of the input directory and all mounted ones
+        let mut roots = vec![(directory.to_path_buf(), PathBuf::new())];
+        for mount in &self.config.mounts {
+            let prefix = PathBuf::from(&mount.prefix);
+            if !prefix.components().all(|c| matches!(c, Component::Normal(_))) {
+                bail!(ErrorKind::InvalidMountPrefix(mount.prefix.clone()));
+            }
+            roots.push((mount.directory.clone(), prefix));
         }
 
-        /// Use the current working directory as a fallback
-        for entry in glob(md_path.to_str().unwrap_or("."))? {
-            self.input_paths.push(
-                InputPaths::new(entry?.to_str()
-                                    .ok_or_else(|| "Unable to stringfy entry in markdown path.")?));
+        let mut outputs = HashMap::new();
+        for (root, prefix) in roots {
+            if !root.is_dir() {
+                bail!(ErrorKind::InputDirectoryMissing(root));
+            }
+            let sources = discovery::find_sources(&root, &self.config, output_directory)?;
+            for page in sources.pages {
+                let renderer = Renderer::for_path(&page, &self.config)
+                    .ok_or("No renderer configured for the source file.")?;
+                let input_path = InputPaths::new(page, renderer, &root, &prefix);
+
+                // Pages of different directories or extensions must not overwrite each other
+                let output = input_path.output_path()?;
+                if let Some(first) = outputs.insert(output.clone(), input_path.path.clone()) {
+                    bail!(ErrorKind::OutputConflict(output, first, input_path.path));
+                }
+                self.input_paths.push(input_path);
+            }
+            for path in sources.assets {
+                let asset = Asset::new(path, &root, &prefix)?;
+                if let Some(first) = outputs.insert(asset.output.clone(), asset.path.clone()) {
+                    bail!(ErrorKind::OutputConflict(asset.output, first, asset.path));
+                }
+                self.assets.push(asset);
+            }
         }
 
         Ok(())
@@ -186,152 +587,706 @@ impl Wiki {
     }
 
 
-    /// Read the content of all files and convert it to HTML
-    pub fn read_content_from_current_paths(&mut self, input_root_dir: &str,
-                                           output_directory: &str) -> Result<()> {
-        if fs::read_dir(input_root_dir)?.count() == 0 {
-            return Err(Error::from("No files given in input paths"));
+    /// Read the content of all files and convert it to HTML. Pages which fail do not abort the
+    /// build but end up in the returned report. The `input_root_dir` must be the directory the
+    /// pages were read from.
+    pub fn read_content_from_current_paths<P, Q>(&mut self, input_root_dir: P,
+                                                 output_directory: Q) -> Result<BuildReport>
+        where P: AsRef<Path>, Q: AsRef<Path>
+    {
+        let input_root_dir = input_root_dir.as_ref();
+        let output_directory = output_directory.as_ref();
+        if !input_root_dir.is_dir() {
+            bail!(ErrorKind::InputDirectoryMissing(input_root_dir.to_path_buf()));
+        }
+        if let Some(ref read) = self.input_directory {
+            if canonicalize(read).ok() != canonicalize(input_root_dir).ok() {
+                bail!(ErrorKind::InputDirectoryMismatch(input_root_dir.to_path_buf(),
+                                                        read.clone()));
+            }
+        }
+        if self.input_paths.is_empty() {
+            bail!(ErrorKind::NoInputFiles(input_root_dir.to_path_buf()));
         }
 
         // Check whether output_directory exists, if not -> create
-        if !Path::new(output_directory).exists() {
-            info!("Creating directory for HMTL output: '{}'.", output_directory);
-            fs::create_dir(output_directory)?;
+        if !output_directory.exists() {
+            info!("Creating directory for HMTL output: '{}'.", output_directory.display());
+            fs::create_dir(output_directory).chain_err(|| {
+                ErrorKind::FileOperation("create the directory".to_owned(),
+                                         output_directory.to_path_buf())
+            })?;
         }
 
-        let sha_file_path = PathBuf::from(output_directory).join(*SHA_FILE);
-        let sha_file = sha_file_path.to_str()
-                           .ok_or_else(|| "Unable to stringify the sha file path.")?;
+        // The pages were discovered skipping the configured output directory, the files
+        // generated into another one within the inputs must not become pages or assets either
+        let output_root = canonicalize(output_directory)?;
+        let within_inputs = Some(input_root_dir.to_path_buf()).into_iter()
+            .chain(self.config.mounts.iter().map(|mount| mount.directory.clone()))
+            .filter_map(|directory| canonicalize(directory).ok())
+            .any(|directory| output_root.starts_with(directory));
+        if within_inputs && canonicalize(&self.config.output_directory).ok() != Some(output_root) {
+            self.discover(input_root_dir, output_directory)?;
+        }
 
-        // Iterate over all available input_paths
-        self.output_paths = self.input_paths.par_iter_mut()
-                                            .filter_map(|ref mut file|
-                                                        file.parse_as_html(input_root_dir,
-                                                                           output_directory,
-                                                                           sha_file)
-                                                        .ok())
-                                            .collect();
+        let manifest_file = output_directory.join(MANIFEST_FILE);
 
-        Filehash::write_file_hash(&mut self.input_paths, sha_file)?;
+        // Every page is rendered with the configured theme
+        let theme = Theme::load(&self.config)?;
+        theme.install(output_directory)?;
+        let source_extensions = self.config.source_extensions();
+        let diagrams = Diagrams::new(&self.config.diagrams,
+                                     Some(output_directory.join(DIAGRAM_CACHE)));
 
-        Ok(())
+        // All pages are read first, since the menu on every page depends on their front matter
+        let load_errors = self.load_pages();
+        let menu = self.build_menu();
+        let breadcrumbs = self.build_breadcrumbs();
+        let fingerprint = self.build_fingerprint(&theme, &menu, &breadcrumbs)?;
+        // The manifest is loaded once and shared by all workers
+        let manifest = Manifest::load(&manifest_file);
+        let context = RenderContext {
+            output_dir: output_directory,
+            manifest: &manifest,
+            theme: &theme,
+            menu: &menu,
+            breadcrumbs: &breadcrumbs,
+            fingerprint: &fingerprint,
+            force: self.force,
+            options: Options {
+                extensions: &source_extensions,
+                math: self.config.markdown.math,
+                diagrams: &diagrams,
+            },
+        };
+
+        // Read all available input_paths and check which of them changed
+        let read_errors: Vec<Option<Error>> = self.input_paths.par_iter_mut()
+            .zip(load_errors.into_par_iter())
+            .map(|(file, load_error)| match load_error {
+                Some(e) => Some(e),
+                None => file.prepare(&context).err(),
+            })
+            .collect();
+
+        // Pages which changed, appeared or disappeared since the last build
+        let current: HashSet<PathBuf> = self.input_paths.iter()
+                                                        .map(|file| dependency::normalize(&file.path))
+                                                        .collect();
+        let mut changed: HashSet<PathBuf> = self.input_paths.iter()
+                                                            .filter(|file| !file.up_to_date)
+                                                            .map(|file| dependency::normalize(&file.path))
+                                                            .collect();
+        changed.extend(manifest.pages.iter()
+                                     .map(|page| dependency::normalize(&page.source))
+                                     .filter(|source| !current.contains(source)));
+
+        // Render all pages which or whose dependencies changed, a failed page stops all pages
+        // not yet started when failing fast
+        let fail_fast = self.fail_fast;
+        let stopped = AtomicBool::new(fail_fast && read_errors.iter().any(Option::is_some));
+        let pages: Vec<PageReport> = self.input_paths.par_iter_mut()
+            .zip(read_errors.into_par_iter())
+            .map(|(file, read_error)| {
+                let result = match read_error {
+                    Some(e) => {
+                        // Pages which could not be loaded keep using the diagrams of their last
+                        // build, so they are neither pruned nor forgotten by the manifest
+                        file.diagrams = manifest.page(&file.path)
+                                                .map_or_else(Vec::new, |page| page.diagrams.clone());
+                        Err(e)
+                    },
+                    None if stopped.load(Ordering::SeqCst) => Ok(PageStatus::Skipped),
+                    None => file.parse_as_html(&context, &changed),
+                }.chain_err(|| ErrorKind::RenderFailed(file.path.clone()));
+                match result {
+                    Ok(status) => file.report(status, None, &current),
+                    Err(e) => {
+                        error!("{}", full_message(&e));
+                        if fail_fast {
+                            stopped.store(true, Ordering::SeqCst);
+                        }
+                        file.report(PageStatus::Failed, Some(e), &current)
+                    },
+                }
+            })
+            .collect();
+        // Copy all static assets which changed
+        let force = self.force;
+        let assets: Vec<PageReport> = self.assets.par_iter_mut()
+            .map(|asset| {
+                let result = asset.copy(&manifest, output_directory, force);
+                if let Err(ref e) = result {
+                    error!("{}", full_message(e));
+                }
+                asset.report(result)
+            })
+            .collect();
+
+        let report = BuildReport::new(pages, assets);
+        self.output_paths = report.pages.iter()
+                                        .filter_map(|page| page.output.clone())
+                                        .collect();
+        // Group the pages of mounted directories within the index
+        self.output_paths.sort();
+
+        // Generated files are remembered as well, so they are removed once disabled
+        let mut generated = theme.outputs()?;
+        generated.extend(self.listing_outputs());
+        self.remove_stale_outputs(output_directory, false)?;
+        Filehash::write_file_hash(&self.input_paths, &self.assets, &generated, &manifest_file)?;
+        // Pages which were not rendered still hold the diagrams of their last build
+        diagrams.prune(self.input_paths.iter().flat_map(|file| &file.diagrams));
+
+        // The listings of the pages are written last, so their failures never lose the manifest
+        self.create_index_tree(output_directory)?;
+        self.write_sitemap(output_directory)?;
+        self.write_feeds(output_directory)?;
+        self.write_recent_changes(output_directory, &theme, &menu, &breadcrumbs)?;
+
+        Ok(report)
+    }
+
+    /// Render all pages on the next build, even if they are up to date
+    pub fn set_force_rebuild(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    /// Stop the next build at the first page which fails instead of rendering all other pages
+    pub fn set_fail_fast(&mut self, fail_fast: bool) {
+        self.fail_fast = fail_fast;
+    }
+
+    /// Hashes everything besides the page content which influences the generated pages: the
+    /// crate version, the theme, the navigation and the renderer settings
+    fn build_fingerprint(&self, theme: &Theme, menu: &Menu, breadcrumbs: &Breadcrumbs)
+                         -> Result<String> {
+        let renderer_settings = toml::to_string(&self.config.markdown)
+            .and_then(|markdown| toml::to_string(&self.config.text).map(|text| markdown + &text))
+            .and_then(|settings| {
+                toml::to_string(&self.config.diagrams).map(|diagrams| settings + &diagrams)
+            })
+            .map_err(|e| format!("Unable to serialize the renderer settings: {}", e))?;
+        let inputs = [env!("CARGO_PKG_VERSION"),
+                      &theme.fingerprint(),
+                      &menu.render(Path::new("")),
+                      &breadcrumbs.fingerprint(),
+                      &self.config.title,
+                      &self.config.base_url,
+                      &renderer_settings];
+        Ok(Filehash::hash(inputs.join("\0").as_bytes()))
+    }
+
+    /// Reads all pages and their front matter, returning the error of every page
+    fn load_pages(&mut self) -> Vec<Option<Error>> {
+        self.input_paths.par_iter_mut().map(|file| file.load().err()).collect()
     }
 
-    /// Creates an index.html with simple tree structure view when no index.md was seen
-    pub fn create_index_tree(&self, output_directory: &str) -> Result<()> {
-        let index_path = Path::new(output_directory).join("index.html");
-        if !index_path.exists() {
-            info!("Creating index.html at {}",
-                  index_path.to_str().ok_or_else(|| "Unable to stringify index path.")?);
-            let mut index_file = File::create(index_path)?;
-            let mut index_str = String::from(include_str!("html/index.template.html"));
-            for output_path in &self.output_paths {
-                index_str.push_str(format!("<li><a href=\"{}\">{}</a></li>\n",
-                                           output_path.to_str()
-                                               .ok_or_else(|| "Unable to stringify output path.")?,
-                                           output_path.file_name()
-                                               .ok_or_else(|| "Unable to extract file name for path")?
-                                               .to_str().ok_or_else(|| "Unable to stringify output path.")?)
-                                   .as_str());
+    /// The site menu of the configured entries and all loaded pages listed in the main menu.
+    /// Pages nested below other pages are added last, so their parents exist.
+    fn build_menu(&self) -> Menu {
+        let mut menu = Menu::new(&self.config.menu);
+        let mut pages: Vec<&InputPaths> = self.input_paths.iter()
+            .filter(|file| !file.front_matter.draft)
+            .filter(|file| file.front_matter.menu.as_ref().is_some_and(|menu| menu == MAIN_MENU))
+            .collect();
+        pages.sort_by_key(|file| file.front_matter.parent.is_some());
+        for file in pages {
+            menu.add_page(&file.title(&file.front_matter), &file.output, file.front_matter.weight,
+                          file.front_matter.parent.as_deref());
+        }
+        menu.sort();
+        menu
+    }
+
+    /// The breadcrumbs titling every directory with a loaded index page by that page, unless it
+    /// is a draft
+    fn build_breadcrumbs(&self) -> Breadcrumbs {
+        let mut breadcrumbs = Breadcrumbs::new(&self.config.title);
+        for file in &self.input_paths {
+            if file.content.is_some() && !file.front_matter.draft &&
+               file.output.file_stem().is_some_and(|s| s == "index") {
+                if let Some(directory) = file.output.parent() {
+                    breadcrumbs.add_section(directory, &file.title(&file.front_matter));
+                }
             }
-            index_file.write_all(index_str.as_bytes())?;
         }
+        breadcrumbs
+    }
+
+    /// Writes the sitemap of all rendered pages and the robots.txt into `output_directory`,
+    /// unless they are disabled or provided as assets
+    fn write_sitemap(&self, output_root: &Path) -> Result<()> {
+        let config = &self.config.sitemap;
+        let base_url = &self.config.base_url;
 
+        if config.enabled && !self.is_asset(Path::new(SITEMAP_FILE)) {
+            if base_url.is_empty() {
+                warn!("Skipping the sitemap, it needs the base_url to be configured.");
+            } else {
+                let rendered: HashSet<&PathBuf> = self.output_paths.iter().collect();
+                let mut entries: Vec<SitemapEntry> = self.input_paths.iter()
+                    .filter(|file| rendered.contains(&file.output) && file.front_matter.is_listed())
+                    .map(|file| SitemapEntry {
+                        output: file.output.clone(),
+                        modified: file.last_modified(),
+                    })
+                    .collect();
+                // The generated index changes together with the newest page
+                if self.generates_index() {
+                    entries.push(SitemapEntry {
+                        output: PathBuf::from(INDEX_FILE),
+                        modified: entries.iter()
+                                         .map(|entry| entry.modified)
+                                         .max()
+                                         .unwrap_or_else(Utc::now),
+                    });
+                }
+                entries.sort_by(|a, b| a.output.cmp(&b.output));
+                let sitemap_path = output_root.join(SITEMAP_FILE);
+                fs::write(&sitemap_path, sitemap::sitemap(base_url, &entries))
+                    .chain_err(|| ErrorKind::FileOperation("write".to_owned(), sitemap_path))?;
+            }
+        }
+        if config.robots && !self.is_asset(Path::new(ROBOTS_FILE)) {
+            let robots_path = output_root.join(ROBOTS_FILE);
+            fs::write(&robots_path, sitemap::robots(config, base_url))
+                .chain_err(|| ErrorKind::FileOperation("write".to_owned(), robots_path))?;
+        }
         Ok(())
     }
 
-    /// Add a directory for storing files to the generated html sites or read stored
-    /// files of existing filestorage. Afterwards file links will be added to the
-    /// generated html site.
-    pub fn read_files(&mut self, file_folder: &str, output: &str) -> Result<()> {
+    /// Returns true if a static asset is copied to `output` within the output directory
+    fn is_asset(&self, output: &Path) -> bool {
+        self.assets.iter().any(|asset| asset.output == output)
+    }
 
-        //create the default file path
-        let output_path = PathBuf::from(output);
-        let file_directory = PathBuf::from(output).join(file_folder);
+    /// Writes the feeds of the site and of every configured section into `output_directory`,
+    /// unless they are provided as assets. Pages which can no longer be read are left out.
+    fn write_feeds(&self, output_directory: &Path) -> Result<()> {
+        let config = &self.config.feeds;
+        if !config.enabled {
+            return Ok(());
+        }
+        if self.config.base_url.is_empty() {
+            warn!("Skipping the feeds, they need the base_url to be configured.");
+            return Ok(());
+        }
 
-        if !Path::new(file_folder).exists(){
-            info!("Creating directory for file input: {:?}.", file_directory.to_str());
-                   fs::create_dir(&file_directory);
+        // The git history is read once per input directory
+        let mut git_dates = HashMap::new();
+        if config.git {
+            for file in &self.input_paths {
+                if !git_dates.contains_key(&file.root) {
+                    git_dates.insert(file.root.clone(), feed::git_dates(&file.root));
+                }
+            }
         }
 
-        for path in &self.input_paths{
-            let mut count = 0;
+        let rendered: HashSet<&PathBuf> = self.output_paths.iter().collect();
+        let mut pages: Vec<(&InputPaths, DateTime<Utc>)> = self.input_paths.iter()
+            .filter(|file| rendered.contains(&file.output) && file.front_matter.is_listed())
+            .map(|file| {
+                let date = file.front_matter.date.or_else(|| {
+                    let relative_path = discovery::relative_path(&file.path, &file.root).ok()?;
+                    git_dates.get(&file.root)?.get(&relative_path).cloned()
+                });
+                (file, date.unwrap_or_else(|| file.last_modified()))
+            })
+            .collect();
+        pages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.output.cmp(&b.0.output)));
 
-            //create the path for the html site or just find it
-            let page_files_path = file_directory.join(&path.path).to_str().ok_or_else(||
-                                  "Couldn't create Path to html file!")?.replace(".md","");
+        let sections = Some(String::new()).into_iter().chain(config.sections.iter().cloned());
+        for section in sections {
+            let directory = PathBuf::from(&section);
+            let entries = pages.iter()
+                .filter(|page| page.0.output.starts_with(&directory))
+                .filter_map(|&(file, updated)| {
+                    let entry = file.read().and_then(|buffer| {
+                        let (front_matter, content) = file.split_front_matter(&buffer)?;
+                        Ok(FeedEntry {
+                            title: file.title(&front_matter),
+                            output: file.output.clone(),
+                            updated,
+                            summary: front_matter.description.clone().unwrap_or_else(|| {
+                                feed::summary(content, config.summary_length)
+                            }),
+                        })
+                    });
+                    entry.map_err(|e| warn!("Skipping '{}' in the feeds: {}", file.path.display(),
+                                            full_message(&e)))
+                         .ok()
+                })
+                .take(config.limit)
+                .collect();
+            let feed = Feed {
+                title: if section.is_empty() {
+                    self.config.title.clone()
+                } else {
+                    format!("{} - {}", self.config.title, section)
+                },
+                base_url: self.config.base_url.clone(),
+                directory: directory.clone(),
+                entries,
+            };
 
-            if !PathBuf::from(page_files_path.as_str()).exists(){
-                info!("Creating directory for {}'s files: {}.", path.path.to_str().ok_or_else(|| "Path not found!")?,
-                                                                page_files_path.as_str());
-                fs::create_dir_all(page_files_path.as_str());
+            let feed_directory = output_directory.join(&directory);
+            create_dir_all(&feed_directory)?;
+            for &(name, ref content) in &[(ATOM_FILE, feed.atom()),
+                                          (RSS_FILE, feed.rss()),
+                                          (JSON_FILE, feed.json()?)] {
+                if self.is_asset(&directory.join(name)) {
+                    continue;
+                }
+                let feed_path = feed_directory.join(name);
+                fs::write(&feed_path, content)
+                    .chain_err(|| ErrorKind::FileOperation("write".to_owned(), feed_path.clone()))?;
             }
+        }
+        Ok(())
+    }
 
-            //add the path
-            let file_path = Path::new(page_files_path.as_str());
-            //read all the files
-            let files = file_path.read_dir()?;
+    /// Returns true if the recent changes are enabled and no page or asset of the same name exists
+    fn writes_recent_changes(&self) -> bool {
+        let recent_file = Path::new(RECENT_FILE);
+        self.config.recent.enabled &&
+        !self.output_paths.iter().chain(self.assets.iter().map(|asset| &asset.output))
+                                 .any(|output| output == recent_file)
+    }
 
-            //now attach the files to the html sites folder
-            for entry in files {
-                let current_entry = match entry {
-                    Ok(entry) => entry,
-                    _ => panic!("Couldn't read the path!"),
-                };
+    /// The files generated besides the pages, assets and theme files: the index, the sitemap,
+    /// the robots.txt, the feeds and the recent changes, as far as they are written
+    fn listing_outputs(&self) -> Vec<PathBuf> {
+        let mut outputs = Vec::new();
+        if self.generates_index() {
+            outputs.push(PathBuf::from(INDEX_FILE));
+        }
+        let sitemap = &self.config.sitemap;
+        let has_base_url = !self.config.base_url.is_empty();
+        if sitemap.enabled && has_base_url {
+            outputs.push(PathBuf::from(SITEMAP_FILE));
+        }
+        if sitemap.robots {
+            outputs.push(PathBuf::from(ROBOTS_FILE));
+        }
+        let feeds = &self.config.feeds;
+        if feeds.enabled && has_base_url {
+            for section in Some(String::new()).iter().chain(feeds.sections.iter()) {
+                for name in &[ATOM_FILE, RSS_FILE, JSON_FILE] {
+                    outputs.push(Path::new(section).join(name));
+                }
+            }
+        }
+        if self.writes_recent_changes() {
+            outputs.push(PathBuf::from(RECENT_FILE));
+        }
+        // Listings provided as assets are copied instead
+        outputs.retain(|output| !self.is_asset(output));
+        outputs
+    }
+
+    /// Writes the recent changes page of the manifest just written into `output_directory`,
+    /// unless it is disabled or a page or asset of the same name exists
+    fn write_recent_changes(&self, output_root: &Path, theme: &Theme, menu: &Menu,
+                            breadcrumbs: &Breadcrumbs) -> Result<()> {
+        let config = &self.config.recent;
+        if !config.enabled {
+            return Ok(());
+        }
+        if !self.writes_recent_changes() {
+            warn!("Skipping the recent changes, '{}' is generated from the input.", RECENT_FILE);
+            return Ok(());
+        }
 
-                let link = format!(
-                    "\n<a href='http://localhost:30000/files/{}/{}'>{}</a><br>\n",
-                    path.path.to_str().ok_or_else(|| "Path not found!")?.replace(".md", ""),
-                    current_entry.file_name().to_str().ok_or_else(|| "Entry is corrupted!")?.replace("\n","."),
-                    current_entry.file_name().to_str().ok_or_else(|| "Entry is corrupted!")?
-                );
-                let mut html_file = OpenOptions::new().read(true).write(true).create(true).
-                    open(output_path.join(&path.path).to_str().ok_or_else(|| "Entry is corrupted!")?.
-                    replace(".md", ".html"))?;
-
-                let mut buffer = String::new();
-
-                html_file.read_to_string(&mut buffer);
-                buffer += &link.as_str();
-                info!("Creating link to {:?} for {:?}", current_entry.file_name(), path.path.to_str()
-                      .ok_or_else(|| "Entry is corrupted!")?.replace(".md", ".html"));
-                html_file.write((&buffer).as_bytes());
-                count += 1;
+        let manifest = Manifest::load(output_root.join(MANIFEST_FILE));
+        let recent_path = output_root.join(RECENT_FILE);
+        fs::write(&recent_path, recent::page(&manifest, config.limit, theme, menu, breadcrumbs))
+            .chain_err(|| ErrorKind::FileOperation("write".to_owned(), recent_path.clone()))
+    }
+
+    /// Removes the HTML files of previous builds whose markdown files were deleted or renamed,
+    /// and the theme files no longer installed, together with directories left empty. Returns the stale files, which are only listed
+    /// but not removed on a `dry_run`.
+    pub fn remove_stale_outputs<P: AsRef<Path>>(&self, output_directory: P, dry_run: bool)
+                                                -> Result<Vec<PathBuf>> {
+        let output_root = output_directory.as_ref();
+
+        let mut current_outputs = HashSet::new();
+        for input_path in &self.input_paths {
+            current_outputs.insert(input_path.output_path()?);
+        }
+        current_outputs.extend(self.assets.iter().map(|asset| asset.output.clone()));
+        current_outputs.extend(Theme::load(&self.config)?.outputs()?);
+        current_outputs.extend(self.listing_outputs());
+
+        let stale_outputs: Vec<PathBuf> = Filehash::read_outputs(output_root.join(MANIFEST_FILE))
+            .into_iter()
+            .filter(|output| !current_outputs.contains(output))
+            .filter(|output| {
+                // A tampered manifest must not remove files outside of the output directory
+                let contained = output.components().all(|c| matches!(c, Component::Normal(_)));
+                if !contained {
+                    warn!("Ignoring the invalid output '{}' of the manifest.", output.display());
+                }
+                contained
+            })
+            .filter(|output| output_root.join(output).is_file())
+            .collect();
+
+        for output in &stale_outputs {
+            let stale_file = output_root.join(output);
+            if dry_run {
+                continue;
+            }
+            info!("Removing stale output: {}", stale_file.display());
+            fs::remove_file(&stale_file)?;
+
+            // Clean up the directories which are empty now
+            let mut directory = stale_file.parent();
+            while let Some(dir) = directory {
+                if dir == output_root || fs::read_dir(dir)?.next().is_some() {
+                    break;
+                }
+                debug!("Removing empty directory: {}", dir.display());
+                fs::remove_dir(dir)?;
+                directory = dir.parent();
             }
-            //check if there are no files attached
-            if count == 0 {
-                info!("No files found so far for {}. Simply add your files to the folders in 'files'.",
-                      path.path.to_str().ok_or_else(|| "Entry is corrupted!")?)
+        }
+
+        Ok(stale_outputs)
+    }
+
+    /// Renders all current input_paths without writing any output
+    pub fn check_current_paths(&mut self) -> Result<()> {
+        let theme = Theme::load(&self.config)?;
+        let extensions = self.config.source_extensions();
+        let diagrams = Diagrams::new(&self.config.diagrams, None);
+        let options = Options {
+            extensions: &extensions,
+            math: self.config.markdown.math,
+            diagrams: &diagrams,
+        };
+
+        // All pages are read first, since the menu on every page depends on their front matter
+        let load_errors = self.load_pages();
+        let menu = self.build_menu();
+        let breadcrumbs = self.build_breadcrumbs();
+
+        let failed = self.input_paths.par_iter()
+                                     .zip(load_errors.into_par_iter())
+                                     .filter(|&(file, ref load_error)| {
+                                         let result = match *load_error {
+                                             Some(ref e) => Err(full_message(e)),
+                                             None => file.content.as_ref()
+                                                 .ok_or_else(|| "The file could not be read.".into())
+                                                 .and_then(|buffer| {
+                                                     file.render(buffer, &theme, &menu,
+                                                                 &breadcrumbs, &options)
+                                                 })
+                                                 .map_err(|e| full_message(&e)),
+                                         };
+                                         match result {
+                                             Ok((rendered, _)) => {
+                                                 for warning in rendered.warnings {
+                                                     warn!("{}: {}", file.path.display(), warning);
+                                                 }
+                                                 false
+                                             },
+                                             Err(message) => {
+                                                 error!("{}", message);
+                                                 true
+                                             },
+                                         }
+                                     })
+                                     .count();
+
+        if failed > 0 {
+            bail!("{} of {} pages failed to render", failed, self.input_paths.len());
+        }
+        info!("All {} pages rendered successfully.", self.input_paths.len());
+        Ok(())
+    }
+
+    /// Creates a new page at the path `page` relative to `input_directory`, appending the
+    /// first configured markdown extension if the path has none
+    pub fn new_page<P, Q>(&self, input_directory: P, page: Q) -> Result<PathBuf>
+        where P: AsRef<Path>, Q: AsRef<Path>
+    {
+        let mut page_path = input_directory.as_ref().join(page);
+        let has_extension = page_path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.config.source_extensions().iter().any(|e| e == extension));
+        if !has_extension {
+            let extension = self.config.markdown.extensions.first()
+                .ok_or("No markdown extensions configured.")?;
+            let mut file_name = page_path.file_name()
+                .ok_or("The page path has no file name.")?
+                .to_os_string();
+            file_name.push(".");
+            file_name.push(extension);
+            page_path.set_file_name(file_name);
+        }
+
+        if page_path.exists() {
+            bail!(ErrorKind::AlreadyExists(page_path));
+        }
+        if let Some(parent) = page_path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let title = page_path.file_stem()
+            .map(|stem| stem.to_string_lossy().replace(['_', '-'], " "))
+            .unwrap_or_default();
+        let mut page_file = File::create(&page_path)?;
+        page_file.write_all(format!("# {}\n", title).as_bytes())?;
+        info!("Created page '{}'", page_path.display());
+
+        Ok(page_path)
+    }
+
+    /// Removes the generated output in `output_directory`
+    pub fn clean<P, Q>(&self, input_directory: P, output_directory: Q) -> Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>
+    {
+        let input_directory = input_directory.as_ref();
+        let output_path = output_directory.as_ref();
+        if !output_path.exists() {
+            info!("Nothing to clean, '{}' does not exist.", output_path.display());
+            return Ok(());
+        }
+
+        // Never delete the sources by accident
+        if input_directory.exists() &&
+           canonicalize(input_directory)?.starts_with(canonicalize(output_path)?) {
+            bail!(ErrorKind::OutputContainsInput(output_path.to_path_buf(),
+                                                 input_directory.to_path_buf()));
+        }
+
+        info!("Removing the output directory '{}'.", output_path.display());
+        fs::remove_dir_all(output_path)?;
+        Ok(())
+    }
+
+    /// Scaffolds a new wiki in `directory` with a sample configuration, index page and theme
+    pub fn init_directory<P: AsRef<Path>>(directory: P) -> Result<()> {
+        let directory = directory.as_ref();
+        let config_path = directory.join(CONFIG_FILE);
+        if config_path.exists() {
+            bail!(ErrorKind::AlreadyExists(config_path));
+        }
+
+        let theme_directory = directory.join("theme");
+        create_dir_all(&theme_directory)?;
+
+        let files = [(config_path, include_str!("init/wiki.toml")),
+                     (directory.join("index.md"), include_str!("init/index.md")),
+                     (theme_directory.join("page.html"), template::PAGE_TEMPLATE),
+                     (theme_directory.join("style.css"), template::STYLESHEET)];
+        for &(ref path, content) in &files {
+            if path.exists() {
+                info!("Keeping existing file '{}'", path.display());
+                continue;
             }
+            info!("Creating '{}'", path.display());
+            File::create(path)?.write_all(content.as_bytes())?;
         }
+
         Ok(())
     }
 
-    /// Create an HTTP server serving the generated files
-    pub fn serve(&self, output_directory: &str) -> Result<()> {
+    /// Returns true if the index is generated, since the input has no index page of its own
+    fn generates_index(&self) -> bool {
+        let index_output = Path::new(INDEX_FILE);
+        !self.output_paths.iter().chain(self.assets.iter().map(|asset| &asset.output))
+                          .any(|output| output == index_output)
+    }
+
+    /// Creates an index.html listing all pages besides drafts, unless the input has its own
+    /// index page. The listing is updated whenever pages were added or removed.
+    pub fn create_index_tree<P: AsRef<Path>>(&self, output_directory: P) -> Result<()> {
+        if !self.generates_index() {
+            return Ok(());
+        }
+
+        // Drafts are rendered, but never listed
+        let drafts: HashSet<&PathBuf> = self.input_paths.iter()
+                                                        .filter(|file| file.front_matter.draft)
+                                                        .map(|file| &file.output)
+                                                        .collect();
+        let mut index_str = String::from(include_str!("html/index.template.html"));
+        for output_path in self.output_paths.iter().filter(|output| !drafts.contains(output)) {
+            index_str.push_str(format!("<li><a href=\"{}\">{}</a></li>\n",
+                                       encoding::url_path(output_path),
+                                       escape_html(&output_path.file_name()
+                                           .ok_or("Unable to extract file name for path")?
+                                           .to_string_lossy()))
+                               .as_str());
+        }
+
+        // The listing is regenerated whenever the pages changed
+        let index_path = output_directory.as_ref().join(INDEX_FILE);
+        if fs::read(&index_path).map(|index| index != index_str.as_bytes()).unwrap_or(true) {
+            info!("Creating index.html at {}", index_path.display());
+            fs::write(&index_path, index_str.as_bytes())
+                .chain_err(|| ErrorKind::FileOperation("write".to_owned(), index_path.clone()))?;
+        }
+        Ok(())
+    }
 
-        // Create a default listening address
-        let addr = "localhost:30000";
-        info!("Listening on {}", addr);
+    /// Create an HTTP server serving the generated files on the given `binding`. The recent
+    /// changes are rendered with the menu and breadcrumbs of the pages read from the input.
+    pub fn serve<P: AsRef<Path>>(&mut self, output_directory: P, binding: &Binding)
+                                 -> Result<()> {
+        info!("Listening on {}", binding);
 
         // Moving the data into the closure
-        let output_directory_string = output_directory.to_owned();
+        let output_directory = output_directory.as_ref().to_path_buf();
+
+        // The recent changes are always up to date with the manifest of the last build
+        let recent = if self.config.recent.enabled {
+            let load_errors = self.load_pages();
+            for (file, error) in self.input_paths.iter().zip(load_errors) {
+                if let Some(e) = error {
+                    warn!("Leaving '{}' out of the menu: {}", file.path.display(),
+                          full_message(&e));
+                }
+            }
+            Some((Theme::load(&self.config)?, self.build_menu(), self.build_breadcrumbs(),
+                  self.config.recent.limit))
+        } else {
+            None
+        };
 
-        // Create a new iron instance
-        Iron::new(move |request: &mut Request| {
+        // Create a new iron handler
+        let handler = move |request: &mut Request| {
                 ///to load files in browser
                 fn get_file(mime_type: &Mime, body: File) -> iron::Response {
                     let mut resp = Response::with((status::Ok, body));
                     resp.headers.set(ContentType(mime_type.to_owned()));
                     resp
                 }
-                let mut path = PathBuf::from(&output_directory_string);
-                // Create the full path
-                for part in request.url.path() {
-                    path.push(part);
+                if let Some((ref theme, ref menu, ref breadcrumbs, limit)) = recent {
+                    if request.url.path() == ["recent"] {
+                        let manifest_file = output_directory.join(MANIFEST_FILE);
+                        let manifest = Manifest::load(&manifest_file);
+                        let page = recent::page(&manifest, limit, theme, menu, breadcrumbs);
+                        return Ok(Response::with((ContentType::html().0, status::Ok, page)));
+                    }
                 }
 
-                // Could use some security validation for the path here.
+                // Create the full path, the decoded path must stay within the output directory.
+                // Hidden files, like the manifest and the diagram cache, are never served.
+                let relative_path = encoding::path_from_url(&request.url.path())
+                    .filter(|path| {
+                        !path.components()
+                             .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                    });
+                let mut path = match relative_path {
+                    Some(relative_path) => output_directory.join(relative_path),
+                    None => return Ok(Response::with((ContentType::html().0,
+                                                      status::NotFound,
+                                                      include_str!("html/404.html")))),
+                };
 
                 // Use a default page for the middleware
                 if path.is_dir() {
@@ -349,37 +1304,33 @@ impl Wiki {
                                                    include_str!("html/404.html")))),
                     };
 
-                match path.to_str(){
-                    Some(name) => {
-
-                        if name.contains(".pdf") {return Ok(get_file(&(*PDF_MIME), f))};
-                        if name.contains(".doc") {return Ok(get_file(&(*DOC_MIME), f))};
-                        if name.contains(".oda") {return Ok(get_file(&(*ODA_MIME), f))};
-                        if name.contains(".zip") {return Ok(get_file(&(*ZIP_MIME), f))};
-                        if name.contains(".wav") {return Ok(get_file(&(*WAV_MIME), f))};
-                        if name.contains(".css") {return Ok(get_file(&(*CSS_MIME), f))};
-                        if name.contains(".mp")  {return Ok(get_file(&(*MPG_MIME), f))};
-                        if name.contains(".avi") {return Ok(get_file(&(*AVI_MIME), f))};
-                        if name.contains(".png") {return Ok(get_file(&(*PNG_MIME), f))};
-                        if name.contains(".jp")  {return Ok(get_file(&(*JPG_MIME), f))};
-                        if name.contains(".gif") {return Ok(get_file(&(*GIF_MIME), f))};
-
-                        if name.contains(".html") {
-                            let mut buffer = String::new();
-                            match f.read_to_string(&mut buffer) {
-                                Ok(v) => v,
-                                _ => return Ok(Response::with((ContentType::html().0,
-                                                               status::InternalServerError,
-                                                               include_str!("html/500.html")))),
-                            };
-                            return Ok(Response::with((ContentType::html().0, status::Ok, buffer)))
-                        }
+                let name = path.to_string_lossy();
 
-                        else {Ok(Response::with((status::Ok, f)))}
-                    },
-                    _ => panic!("Invalid Path."),
+                if name.contains(".pdf") {return Ok(get_file(&PDF_MIME, f))};
+                if name.contains(".doc") {return Ok(get_file(&DOC_MIME, f))};
+                if name.contains(".oda") {return Ok(get_file(&ODA_MIME, f))};
+                if name.contains(".zip") {return Ok(get_file(&ZIP_MIME, f))};
+                if name.contains(".wav") {return Ok(get_file(&WAV_MIME, f))};
+                if name.contains(".css") {return Ok(get_file(&CSS_MIME, f))};
+                if name.contains(".mp")  {return Ok(get_file(&MPG_MIME, f))};
+                if name.contains(".avi") {return Ok(get_file(&AVI_MIME, f))};
+                if name.contains(".png") {return Ok(get_file(&PNG_MIME, f))};
+                if name.contains(".jp")  {return Ok(get_file(&JPG_MIME, f))};
+                if name.contains(".gif") {return Ok(get_file(&GIF_MIME, f))};
+
+                if name.contains(".html") {
+                    let mut buffer = String::new();
+                    match f.read_to_string(&mut buffer) {
+                        Ok(v) => v,
+                        _ => return Ok(Response::with((ContentType::html().0,
+                                                       status::InternalServerError,
+                                                       include_str!("html/500.html")))),
+                    };
+                    Ok(Response::with((ContentType::html().0, status::Ok, buffer)))
+                } else {
+                    Ok(Response::with((status::Ok, f)))
                 }
-            }).http(addr)?;
-        Ok(())
+            };
+        server::listen(handler, binding)
     }
 }
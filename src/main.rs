@@ -6,6 +6,7 @@ extern crate clap;
 #[macro_use]
 extern crate log;
 extern crate glob;
+extern crate ignore;
 extern crate iron;
 extern crate wikilib;
 #[macro_use]
@@ -14,16 +15,19 @@ extern crate error_chain;
 pub mod error;
 
 use wikilib::Wiki;
+use wikilib::config::Config;
 use wikilib::error::*;
 
-use clap::App;
+use clap::{App, ArgMatches};
 use log::LogLevel;
 
+use std::env;
+use std::path::PathBuf;
 use std::process::exit;
 
 fn main() {
     if let Err(error) = run() {
-        error!("{}", error);
+        error!("{}", full_message(&error));
         exit(1);
     }
 }
@@ -42,31 +46,132 @@ fn run() -> Result<()> {
         _ => LogLevel::Trace,
     };
 
-    // Get the input directory
-    let input_directory = matches.value_of("input_directory")
-        .ok_or_else(|| "CLI parameter 'input_directory' missing.")?;
+    // The logger is global, so it is set up before the configuration gets loaded
+    Wiki::new().init_logging(log_level)?;
+
+    match matches.subcommand() {
+        ("build", Some(sub_matches)) => {
+            build(&load_config(sub_matches)?,
+                  sub_matches.is_present("dry_run"),
+                  sub_matches.is_present("force"),
+                  sub_matches.is_present("fail_fast"),
+                  sub_matches.value_of("report_format").unwrap_or("human"))
+        }
+        ("serve", Some(sub_matches)) => serve(&load_config(sub_matches)?),
+        ("check", Some(sub_matches)) => check(&load_config(sub_matches)?),
+        ("new", Some(sub_matches)) => {
+            let page = sub_matches.value_of("page")
+                .ok_or_else(|| "CLI parameter 'page' missing.")?;
+            let config = load_config(sub_matches)?;
+            let input_directory = config.input_directory.clone();
+            Wiki::with_config(config).new_page(&input_directory, page)?;
+            Ok(())
+        }
+        ("clean", Some(sub_matches)) => {
+            let config = load_config(sub_matches)?;
+            Wiki::with_config(config.clone()).clean(&config.input_directory,
+                                                    &config.output_directory)
+        }
+        ("init", Some(sub_matches)) => {
+            Wiki::init_directory(sub_matches.value_of_os("directory").unwrap_or(".".as_ref()))
+        }
+        _ => bail!("No subcommand given, see `--help`."),
+    }
+}
 
-    // Get the output directory
-    let output_directory = matches.value_of("output_directory")
-        .ok_or_else(|| "CLI parameter 'output_directory' missing.")?;
+// Load the configuration and apply the CLI parameters of the subcommand on top of it
+fn load_config(matches: &ArgMatches) -> Result<Config> {
+    // Get the input directory, which contains the optional configuration file
+    let input_arg = matches.value_of_os("input_directory");
+    let config_directory = match input_arg {
+        Some(input_directory) => PathBuf::from(input_directory),
+        None => env::var_os("WIKI_INPUT_DIRECTORY").map_or_else(|| PathBuf::from("."),
+                                                                PathBuf::from),
+    };
 
-    let enable_httpd = matches.is_present("www");
+    let mut config = Config::load(&config_directory)?;
+    if let Some(input_directory) = input_arg {
+        config.input_directory = PathBuf::from(input_directory);
+    }
+    if let Some(output_directory) = matches.value_of_os("output_directory") {
+        config.output_directory = PathBuf::from(output_directory);
+    }
+    if let Some(file_directory) = matches.value_of_os("file_directory") {
+        config.file_directory = PathBuf::from(file_directory);
+    }
+    if let Some(address) = matches.value_of("address") {
+        config.server.address = address.to_owned();
+    }
+    if matches.is_present("port") {
+        config.server.port = value_t!(matches, "port", u16)
+            .map_err(|e| format!("CLI parameter 'port' invalid: {}", e))?;
+    }
+    if matches.is_present("address") || matches.is_present("port") {
+        // An explicitly given TCP address wins over sockets from the configuration
+        config.server.unix_socket = None;
+        config.server.inherit_socket = false;
+    }
+    if let Some(path) = matches.value_of_os("unix_socket") {
+        config.server.unix_socket = Some(PathBuf::from(path));
+    }
+    if matches.is_present("inherit_socket") {
+        config.server.unix_socket = None;
+        config.server.inherit_socket = true;
+    }
 
-    let file_directory = matches.value_of("file_directory")
-        .ok_or_else(|| "CLI parameter 'file_directory' missing")?;
+    Ok(config)
+}
 
-    // Do first processing steps
-    let mut wiki = Wiki::new();
+// Generate the HTML output, or only list the stale outputs on a `dry_run`
+fn build(config: &Config, dry_run: bool, force: bool, fail_fast: bool, report_format: &str)
+         -> Result<()> {
+    let input_directory = config.input_directory.as_path();
+    let output_directory = config.output_directory.as_path();
+    let mut wiki = Wiki::with_config(config.clone());
+    wiki.set_force_rebuild(force);
+    wiki.set_fail_fast(fail_fast);
 
-    wiki.init_logging(log_level)?;
     wiki.read_from_directory(input_directory)?;
-    wiki.read_content_from_current_paths(input_directory, output_directory)?;
-    wiki.read_files(file_directory, output_directory);
-    wiki.create_index_tree(output_directory)?;
+    if dry_run {
+        let stale_outputs = wiki.remove_stale_outputs(output_directory, true)?;
+        for output in &stale_outputs {
+            println!("Would remove stale output: {}",
+                     output_directory.join(output).display());
+        }
+        info!("{} stale outputs would be removed.", stale_outputs.len());
+        return Ok(());
+    }
+    let report = wiki.read_content_from_current_paths(input_directory, output_directory)?;
 
-    if enable_httpd {
-        wiki.serve(output_directory)?;
+    match report_format {
+        "json" => println!("{}", report.to_json()?),
+        _ => println!("{}", report),
+    }
+    if report.has_failures() {
+        bail!("{} of {} pages failed to render, {} assets failed to copy",
+              report.summary.failed, report.summary.total, report.summary.assets_failed);
     }
 
     Ok(())
 }
+
+// Serve the already generated HTML output
+fn serve(config: &Config) -> Result<()> {
+    if !config.output_directory.is_dir() {
+        bail!("The output directory '{}' does not exist, run `wiki build` first.",
+              config.output_directory.display());
+    }
+    // The recent changes show the menu of the pages
+    let mut wiki = Wiki::with_config(config.clone());
+    if config.recent.enabled {
+        wiki.read_from_directory(&config.input_directory)?;
+    }
+    wiki.serve(&config.output_directory, &config.server.binding())
+}
+
+// Render all pages without writing any output
+fn check(config: &Config) -> Result<()> {
+    let mut wiki = Wiki::with_config(config.clone());
+    wiki.read_from_directory(&config.input_directory)?;
+    wiki.check_current_paths()
+}
@@ -0,0 +1,683 @@
+//! Everything related to rendering LaTeX math into MathML
+//!
+//! Formulas within `$..$` are rendered inline, formulas within `$$..$$` as block. They are taken
+//! out of the markdown before it is rendered and replaced by MathML afterwards, so the markdown
+//! renderer never sees them. Code blocks and spans are left alone and `\$` is a literal dollar.
+//! The supported LaTeX covers the common commands for formulas: fractions, roots, scripts,
+//! greek letters, operators, accents, fonts, delimiters and matrices.
+
+use renderer::{escape_html, Placeholders};
+
+/// A formula taken out of the markdown source
+#[derive(Clone, Debug, PartialEq)]
+pub struct Formula {
+    /// The LaTeX source, without the dollars
+    pub source: String,
+    /// Display math is rendered as block
+    pub display: bool,
+}
+
+/// Takes all formulas out of the markdown `content`, replacing them by the `placeholders`
+pub fn extract(content: &str, placeholders: &Placeholders) -> (String, Vec<Formula>) {
+    let mut formulas = Vec::new();
+
+    // Formulas may span several lines of a paragraph, so the text outside code is scanned as whole
+    let (text, code) = split_code(content);
+    let mut result = String::with_capacity(content.len());
+    for (index, part) in text.iter().enumerate() {
+        replace_formulas(part, placeholders, &mut formulas, &mut result);
+        if let Some(code) = code.get(index) {
+            result.push_str(code);
+        }
+    }
+    (result, formulas)
+}
+
+/// The end of the line starting at `start` within `source`, after its line break
+fn end_of_line(source: &str, start: usize) -> usize {
+    source[start..].find('\n').map_or(source.len(), |end| start + end + 1)
+}
+
+/// Splits `source` into the text outside code and the code between, alternating: text, code,
+/// text and so on
+fn split_code(source: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut text = Vec::new();
+    let mut code = Vec::new();
+    let mut start = 0;
+    let mut position = 0;
+    let mut previous_blank = true;
+    let bytes = source.as_bytes();
+
+    while position < source.len() {
+        let at_line_start = position == 0 || bytes[position - 1] == b'\n';
+        if at_line_start {
+            let line_end = end_of_line(source, position);
+            let line = &source[position..line_end];
+            let trimmed = line.trim_start_matches(' ');
+            let indentation = line.len() - trimmed.len();
+            let block_end = if indentation < 4 && (trimmed.starts_with("```") ||
+                                                   trimmed.starts_with("~~~")) {
+                // A fenced block lasts until a fence of the same character, at least as long
+                // as the opening one, or the end
+                let fence = trimmed.as_bytes()[0];
+                let length = trimmed.bytes().take_while(|&b| b == fence).count();
+                let mut end = line_end;
+                while end < source.len() {
+                    let next_end = end_of_line(source, end);
+                    let next = &source[end..next_end];
+                    let next_trimmed = next.trim_start_matches(' ');
+                    let run = next_trimmed.bytes().take_while(|&b| b == fence).count();
+                    end = next_end;
+                    if next.len() - next_trimmed.len() < 4 && run >= length &&
+                       next_trimmed[run..].trim().is_empty() {
+                        break;
+                    }
+                }
+                Some(end)
+            } else if previous_blank && (indentation >= 4 || line.starts_with('\t')) &&
+                      !line.trim().is_empty() {
+                // An indented block lasts until the next line which is neither indented nor
+                // blank, blank lines between the indented ones belong to it
+                let mut end = line_end;
+                let mut next_start = line_end;
+                while next_start < source.len() {
+                    let next_end = end_of_line(source, next_start);
+                    let next = &source[next_start..next_end];
+                    if !next.trim().is_empty() {
+                        if !next.starts_with("    ") && !next.starts_with('\t') {
+                            break;
+                        }
+                        end = next_end;
+                    }
+                    next_start = next_end;
+                }
+                Some(end)
+            } else {
+                None
+            };
+            previous_blank = line.trim().is_empty();
+            if let Some(end) = block_end {
+                text.push(&source[start..position]);
+                code.push(&source[position..end]);
+                start = end;
+                position = end;
+                continue;
+            }
+        }
+
+        if bytes[position] == b'\\' {
+            position += 1 + source[position + 1..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if bytes[position] == b'`' {
+            // A code span ends at the next run of backticks of the same length
+            let run = source[position..].bytes().take_while(|&b| b == b'`').count();
+            let delimiter = &source[position..position + run];
+            let mut search = position + run;
+            let mut end = None;
+            while let Some(found) = source[search..].find(delimiter) {
+                let candidate = search + found;
+                let length = source[candidate..].bytes().take_while(|&b| b == b'`').count();
+                if length == run {
+                    end = Some(candidate + run);
+                    break;
+                }
+                search = candidate + length;
+            }
+            match end {
+                Some(end) => {
+                    text.push(&source[start..position]);
+                    code.push(&source[position..end]);
+                    start = end;
+                    position = end;
+                }
+                None => position += run,
+            }
+            continue;
+        }
+        position += source[position..].chars().next().map_or(1, char::len_utf8);
+    }
+    text.push(&source[start..]);
+    (text, code)
+}
+
+/// Replaces the formulas within the markdown `text` without code by the `placeholders`
+fn replace_formulas(text: &str, placeholders: &Placeholders, formulas: &mut Vec<Formula>,
+                    result: &mut String) {
+    let mut rest = text;
+    while let Some(position) = rest.find(['$', '\\']) {
+        result.push_str(&rest[..position]);
+        let candidate = &rest[position..];
+        if let Some(escaped) = candidate.strip_prefix("\\$") {
+            result.push('$');
+            rest = escaped;
+            continue;
+        }
+        if let Some(escaped) = candidate.strip_prefix('\\') {
+            let length = escaped.chars().next().map_or(0, char::len_utf8);
+            result.push('\\');
+            result.push_str(&escaped[..length]);
+            rest = &escaped[length..];
+            continue;
+        }
+
+        let display = candidate.starts_with("$$");
+        let delimiter = if display { "$$" } else { "$" };
+        match find_closing(&candidate[delimiter.len()..], display) {
+            Some(end) => {
+                let source = &candidate[delimiter.len()..delimiter.len() + end];
+                result.push_str(&placeholders.get(formulas.len()));
+                formulas.push(Formula { source: source.trim().to_owned(), display });
+                rest = &candidate[2 * delimiter.len() + end..];
+            }
+            None => {
+                result.push_str(delimiter);
+                rest = &candidate[delimiter.len()..];
+            }
+        }
+    }
+    result.push_str(rest);
+}
+
+/// Finds the closing dollars of a formula starting at `text`. Inline formulas must neither
+/// start nor end with whitespace, must not be followed by a digit and must not span paragraphs,
+/// so prices like $5 and $10 stay text.
+fn find_closing(text: &str, display: bool) -> Option<usize> {
+    if display {
+        let end = find_unescaped(text, "$$")?;
+        return if text[..end].contains("\n\n") { None } else { Some(end) };
+    }
+    if text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut offset = 0;
+    while let Some(found) = find_unescaped(&text[offset..], "$") {
+        let end = offset + found;
+        let content = &text[..end];
+        if content.is_empty() || content.contains("\n\n") {
+            return None;
+        }
+        let followed_by_digit = text[end + 1..].starts_with(|c: char| c.is_ascii_digit());
+        if !content.ends_with(char::is_whitespace) && !followed_by_digit {
+            return Some(end);
+        }
+        offset = end + 1;
+    }
+    None
+}
+
+/// Finds `pattern` within `text`, skipping backslash escapes
+fn find_unescaped(text: &str, pattern: &str) -> Option<usize> {
+    let mut position = 0;
+    while position < text.len() {
+        if text[position..].starts_with('\\') {
+            position += 1 + text[position + 1..].chars().next().map_or(0, char::len_utf8);
+        } else if text[position..].starts_with(pattern) {
+            return Some(position);
+        } else {
+            position += text[position..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// Replaces the `placeholders` within the rendered `html` by the MathML of the `formulas`.
+/// Invalid formulas are shown as marked source and their errors are added to `warnings`.
+pub fn insert(html: &str, formulas: &[Formula], placeholders: &Placeholders,
+              warnings: &mut Vec<String>) -> String {
+    let mut html = html.to_owned();
+    for (index, formula) in formulas.iter().enumerate() {
+        let rendered = match to_mathml(&formula.source, formula.display) {
+            Ok(mathml) => mathml,
+            Err(message) => {
+                let delimiter = if formula.display { "$$" } else { "$" };
+                warnings.push(format!("Invalid math '{}': {}", formula.source, message));
+                format!("<code class=\"math-error\" title=\"{}\">{}{}{}</code>",
+                        escape_html(&message), delimiter, escape_html(&formula.source), delimiter)
+            }
+        };
+        html = placeholders.replace(&html, index, &rendered, formula.display);
+    }
+    html
+}
+
+/// Renders the LaTeX formula `source` into a MathML element
+pub fn to_mathml(source: &str, display: bool) -> Result<String, String> {
+    let mut parser = Parser { chars: source.chars().collect(), position: 0, display };
+    let content = parser.expression()?;
+    match parser.peek() {
+        None => {}
+        Some('}') => return Err("Unexpected '}'".to_owned()),
+        Some(_) => return Err(format!("Unexpected '{}'", parser.rest_token())),
+    }
+    Ok(format!("<math xmlns=\"http://www.w3.org/1998/Math/MathML\"{}>{}</math>",
+               if display { " display=\"block\"" } else { "" }, content))
+}
+
+/// A recursive descent parser of LaTeX formulas
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    display: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// The command at the current position without consuming it, like `\frac` or `\,`
+    fn peek_command(&self) -> Option<String> {
+        if self.peek() != Some('\\') {
+            return None;
+        }
+        let letters: String = self.chars[self.position + 1..].iter()
+                                                              .take_while(|c| c.is_ascii_alphabetic())
+                                                              .collect();
+        if letters.is_empty() {
+            self.chars.get(self.position + 1).map(|c| c.to_string())
+        } else {
+            Some(letters)
+        }
+    }
+
+    /// Consumes the command at the current position
+    fn command(&mut self) -> Result<String, String> {
+        let command = self.peek_command().ok_or("Incomplete command '\\'")?;
+        self.position += 1 + command.chars().count();
+        Ok(command)
+    }
+
+    /// The next token for error messages
+    fn rest_token(&self) -> String {
+        match self.peek_command() {
+            Some(command) => format!("\\{}", command),
+            None => self.peek().map(|c| c.to_string()).unwrap_or_default(),
+        }
+    }
+
+    /// Returns true if the current position ends an expression
+    fn at_end(&self) -> bool {
+        match self.peek() {
+            None | Some('}') | Some('&') => true,
+            Some('\\') => matches!(self.peek_command().as_deref(), Some("\\") | Some("end") |
+                                                                     Some("right")),
+            _ => false,
+        }
+    }
+
+    /// Parses terms until the end of the expression
+    fn expression(&mut self) -> Result<String, String> {
+        let mut terms = String::new();
+        loop {
+            self.skip_whitespace();
+            if self.at_end() {
+                return Ok(terms);
+            }
+            terms.push_str(&self.term()?);
+        }
+    }
+
+    /// Parses an atom together with its sub- and superscripts
+    fn term(&mut self) -> Result<String, String> {
+        let (base, limits) = self.atom()?;
+        let mut subscript = None;
+        let mut superscript = None;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('_') if subscript.is_none() => {
+                    self.position += 1;
+                    subscript = Some(self.argument()?);
+                }
+                Some('^') if superscript.is_none() => {
+                    self.position += 1;
+                    superscript = Some(self.argument()?);
+                }
+                Some('_') | Some('^') => return Err("Double sub- or superscript".to_owned()),
+                _ => break,
+            }
+        }
+        let (under, over, both) = if limits && self.display {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+        Ok(match (subscript, superscript) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, base, sub),
+            (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, base, sup),
+            (Some(sub), Some(sup)) => format!("<{0}>{1}{2}{3}</{0}>", both, base, sub, sup),
+        })
+    }
+
+    /// Parses the argument of a command or script: a group or a single atom
+    fn argument(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err("Missing argument".to_owned()),
+            Some('{') => self.group(),
+            Some('}') | Some('^') | Some('_') | Some('&') => {
+                Err(format!("Missing argument before '{}'", self.rest_token()))
+            }
+            _ => self.atom().map(|(atom, _)| atom),
+        }
+    }
+
+    /// Parses a group in braces as row
+    fn group(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let content = self.expression()?;
+        if self.peek() != Some('}') {
+            return Err("Missing '}'".to_owned());
+        }
+        self.position += 1;
+        Ok(format!("<mrow>{}</mrow>", content))
+    }
+
+    /// Parses the text argument of commands like `\text`
+    fn text_argument(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.peek() != Some('{') {
+            return Err("Missing '{' of a text".to_owned());
+        }
+        let start = self.position + 1;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.position += 1;
+                        return Ok(self.chars[start..self.position - 1].iter().collect());
+                    }
+                }
+                _ => {}
+            }
+            self.position += 1;
+        }
+        Err("Missing '}'".to_owned())
+    }
+
+    /// Parses a single atom, returning its MathML and whether scripts are placed as limits
+    fn atom(&mut self) -> Result<(String, bool), String> {
+        let c = self.peek().ok_or("Unexpected end")?;
+        if c == '\\' {
+            return self.command_atom();
+        }
+        self.position += 1;
+        Ok((match c {
+            '{' => {
+                self.position -= 1;
+                self.group()?
+            }
+            '0'..='9' | '.' => {
+                let mut number = c.to_string();
+                while let Some(next) = self.peek().filter(|n| n.is_ascii_digit() || *n == '.') {
+                    number.push(next);
+                    self.position += 1;
+                }
+                format!("<mn>{}</mn>", number)
+            }
+            '\'' => "<mo>′</mo>".to_owned(),
+            '^' | '_' => return Err(format!("Missing base before '{}'", c)),
+            '}' => return Err("Unexpected '}'".to_owned()),
+            '#' | '%' | '~' | '$' => return Err(format!("Unexpected '{}'", c)),
+            c if c.is_alphabetic() => format!("<mi>{}</mi>", c),
+            c => format!("<mo>{}</mo>", escape_html(&c.to_string())),
+        }, false))
+    }
+
+    /// Parses an atom starting with a command
+    fn command_atom(&mut self) -> Result<(String, bool), String> {
+        let command = self.command()?;
+        let name = command.as_str();
+        if let Some(symbol) = identifier(name) {
+            return Ok((format!("<mi>{}</mi>", symbol), false));
+        }
+        if let Some(symbol) = operator(name) {
+            return Ok((format!("<mo>{}</mo>", escape_html(symbol)), false));
+        }
+        if let Some(symbol) = large_operator(name) {
+            return Ok((format!("<mo>{}</mo>", symbol), symbol != "∫" && symbol != "∮"));
+        }
+        if FUNCTIONS.contains(&name) {
+            let limits = ["lim", "max", "min", "sup", "inf", "det", "gcd"].contains(&name);
+            return Ok((format!("<mi>{}</mi>", name), limits));
+        }
+        Ok((match name {
+            "," | ":" | ";" | " " => "<mspace width=\"0.2em\"/>".to_owned(),
+            "!" => String::new(),
+            "quad" => "<mspace width=\"1em\"/>".to_owned(),
+            "qquad" => "<mspace width=\"2em\"/>".to_owned(),
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" => {
+                let n = self.argument()?;
+                let k = self.argument()?;
+                format!("<mrow><mo>(</mo><mfrac linethickness=\"0\">{}{}</mfrac><mo>)</mo></mrow>",
+                        n, k)
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.peek() == Some('[') {
+                    let start = self.position + 1;
+                    let end = self.chars[start..].iter().position(|&c| c == ']')
+                        .ok_or("Missing ']' of a root")? + start;
+                    let index: String = self.chars[start..end].iter().collect();
+                    self.position = end + 1;
+                    let radicand = self.argument()?;
+                    format!("<mroot>{}{}</mroot>", radicand,
+                            to_row(&index, self.display)?)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.argument()?)
+                }
+            }
+            "text" | "textrm" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape_html(&self.text_argument()?))
+            }
+            "operatorname" => {
+                format!("<mi mathvariant=\"normal\">{}</mi>", escape_html(&self.text_argument()?))
+            }
+            "mathrm" | "mathbf" | "mathit" | "mathbb" | "mathcal" | "mathsf" | "mathtt" |
+            "mathfrak" => {
+                let variant = match name {
+                    "mathrm" => "normal",
+                    "mathbf" => "bold",
+                    "mathit" => "italic",
+                    "mathbb" => "double-struck",
+                    "mathcal" => "script",
+                    "mathsf" => "sans-serif",
+                    "mathtt" => "monospace",
+                    _ => "fraktur",
+                };
+                format!("<mstyle mathvariant=\"{}\">{}</mstyle>", variant, self.argument()?)
+            }
+            "hat" | "widehat" | "bar" | "overline" | "vec" | "dot" | "ddot" | "tilde" |
+            "widetilde" => {
+                let accent = match name {
+                    "hat" | "widehat" => "^",
+                    "bar" | "overline" => "¯",
+                    "vec" => "→",
+                    "dot" => "˙",
+                    "ddot" => "¨",
+                    _ => "~",
+                };
+                format!("<mover accent=\"true\">{}<mo>{}</mo></mover>", self.argument()?, accent)
+            }
+            "underline" => {
+                format!("<munder accentunder=\"true\">{}<mo>_</mo></munder>", self.argument()?)
+            }
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" => {
+                let delimiter = self.delimiter()?;
+                if name == "left" {
+                    let content = self.expression()?;
+                    if self.peek_command().as_deref() != Some("right") {
+                        return Err("Missing \\right".to_owned());
+                    }
+                    self.command()?;
+                    let closing = self.delimiter()?;
+                    format!("<mrow>{}{}{}</mrow>", delimiter, content, closing)
+                } else if name == "right" {
+                    return Err("Unexpected \\right".to_owned());
+                } else {
+                    delimiter
+                }
+            }
+            "begin" => self.environment()?,
+            "end" => return Err("Unexpected \\end".to_owned()),
+            "\\" => return Err("Unexpected line break".to_owned()),
+            "{" | "}" | "|" | "#" | "%" | "&" | "_" | "$" => {
+                format!("<mo>{}</mo>", escape_html(if name == "|" { "‖" } else { name }))
+            }
+            _ => return Err(format!("Unknown command '\\{}'", name)),
+        }, false))
+    }
+
+    /// Parses the delimiter after `\left` or `\right`, `.` is none
+    fn delimiter(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let symbol = match self.peek() {
+            Some('\\') => {
+                let command = self.command()?;
+                match command.as_str() {
+                    "{" | "}" => command,
+                    "|" => "‖".to_owned(),
+                    name => operator(name).ok_or(format!("Invalid delimiter '\\{}'", name))?
+                                          .to_owned(),
+                }
+            }
+            Some('.') => {
+                self.position += 1;
+                return Ok(String::new());
+            }
+            Some(c) if "()[]|/<>".contains(c) => {
+                self.position += 1;
+                c.to_string()
+            }
+            _ => return Err("Missing delimiter".to_owned()),
+        };
+        Ok(format!("<mo stretchy=\"true\">{}</mo>", escape_html(&symbol)))
+    }
+
+    /// Parses a matrix or cases environment after `\begin`
+    fn environment(&mut self) -> Result<String, String> {
+        let name = self.text_argument()?;
+        let (open, close) = match name.as_str() {
+            "matrix" | "aligned" | "align" | "align*" => ("", ""),
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "vmatrix" => ("|", "|"),
+            "cases" => ("{", ""),
+            _ => return Err(format!("Unknown environment '{}'", name)),
+        };
+
+        let mut rows = String::new();
+        let mut row = String::new();
+        loop {
+            let cell = self.expression()?;
+            row.push_str(&format!("<mtd>{}</mtd>", cell));
+            match self.peek() {
+                Some('&') => self.position += 1,
+                Some('\\') if self.peek_command().as_deref() == Some("\\") => {
+                    self.command()?;
+                    rows.push_str(&format!("<mtr>{}</mtr>", row));
+                    row.clear();
+                }
+                Some('\\') if self.peek_command().as_deref() == Some("end") => {
+                    self.command()?;
+                    let end = self.text_argument()?;
+                    if end != name {
+                        return Err(format!("\\begin{{{}}} ended by \\end{{{}}}", name, end));
+                    }
+                    rows.push_str(&format!("<mtr>{}</mtr>", row));
+                    break;
+                }
+                None => return Err(format!("Missing \\end{{{}}}", name)),
+                _ => return Err(format!("Unexpected '{}'", self.rest_token())),
+            }
+        }
+
+        let table = format!("<mtable>{}</mtable>", rows);
+        let fence = |symbol: &str| if symbol.is_empty() {
+            String::new()
+        } else {
+            format!("<mo>{}</mo>", escape_html(symbol))
+        };
+        Ok(format!("<mrow>{}{}{}</mrow>", fence(open), table, fence(close)))
+    }
+}
+
+/// Renders the nested formula `source` into a row
+fn to_row(source: &str, display: bool) -> Result<String, String> {
+    let mut parser = Parser { chars: source.chars().collect(), position: 0, display };
+    let content = parser.expression()?;
+    if parser.peek().is_some() {
+        return Err(format!("Unexpected '{}'", parser.rest_token()));
+    }
+    Ok(format!("<mrow>{}</mrow>", content))
+}
+
+/// The functions written upright
+const FUNCTIONS: &[&str] = &["sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos",
+                             "arctan", "sinh", "cosh", "tanh", "log", "ln", "lg", "exp", "lim",
+                             "max", "min", "sup", "inf", "det", "gcd", "deg", "dim", "ker", "arg",
+                             "Pr"];
+
+/// The symbol of identifier commands like greek letters
+fn identifier(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "alpha" => "α", "beta" => "β", "gamma" => "γ", "delta" => "δ", "epsilon" => "ϵ",
+        "varepsilon" => "ε", "zeta" => "ζ", "eta" => "η", "theta" => "θ", "vartheta" => "ϑ",
+        "iota" => "ι", "kappa" => "κ", "lambda" => "λ", "mu" => "μ", "nu" => "ν", "xi" => "ξ",
+        "pi" => "π", "varpi" => "ϖ", "rho" => "ρ", "varrho" => "ϱ", "sigma" => "σ",
+        "varsigma" => "ς", "tau" => "τ", "upsilon" => "υ", "phi" => "ϕ", "varphi" => "φ",
+        "chi" => "χ", "psi" => "ψ", "omega" => "ω", "Gamma" => "Γ", "Delta" => "Δ",
+        "Theta" => "Θ", "Lambda" => "Λ", "Xi" => "Ξ", "Pi" => "Π", "Sigma" => "Σ",
+        "Upsilon" => "Υ", "Phi" => "Φ", "Psi" => "Ψ", "Omega" => "Ω", "infty" => "∞",
+        "partial" => "∂", "nabla" => "∇", "emptyset" => "∅", "varnothing" => "∅", "ell" => "ℓ",
+        "hbar" => "ℏ", "Re" => "ℜ", "Im" => "ℑ", "aleph" => "ℵ",
+        _ => return None,
+    })
+}
+
+/// The symbol of operator and relation commands
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "cdot" => "⋅", "times" => "×", "div" => "÷", "pm" => "±", "mp" => "∓", "ast" => "∗",
+        "star" => "⋆", "circ" => "∘", "bullet" => "∙", "leq" | "le" => "≤", "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠", "approx" => "≈", "equiv" => "≡", "sim" => "∼", "simeq" => "≃",
+        "cong" => "≅", "propto" => "∝", "ll" => "≪", "gg" => "≫", "in" => "∈",
+        "notin" => "∉", "ni" => "∋", "subset" => "⊂", "subseteq" => "⊆", "supset" => "⊃",
+        "supseteq" => "⊇", "cup" => "∪", "cap" => "∩", "setminus" => "∖", "forall" => "∀",
+        "exists" => "∃", "neg" | "lnot" => "¬", "land" | "wedge" => "∧", "lor" | "vee" => "∨",
+        "oplus" => "⊕", "otimes" => "⊗", "to" | "rightarrow" => "→", "leftarrow" => "←",
+        "gets" => "←", "leftrightarrow" => "↔", "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐", "Leftrightarrow" | "iff" => "⇔", "mapsto" => "↦", "uparrow" => "↑",
+        "downarrow" => "↓", "ldots" | "dots" => "…", "cdots" => "⋯", "vdots" => "⋮",
+        "ddots" => "⋱", "langle" => "⟨", "rangle" => "⟩", "lfloor" => "⌊", "rfloor" => "⌋",
+        "lceil" => "⌈", "rceil" => "⌉", "mid" => "∣", "parallel" => "∥", "perp" => "⊥",
+        "angle" => "∠", "triangle" => "△", "vert" => "|", "Vert" => "‖", "lbrace" => "{",
+        "rbrace" => "}", "lbrack" => "[", "rbrack" => "]", "colon" => ":", "prime" => "′",
+        _ => return None,
+    })
+}
+
+/// The symbol of large operators, whose scripts are placed as limits in display math
+fn large_operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "sum" => "∑", "prod" => "∏", "coprod" => "∐", "int" => "∫", "oint" => "∮",
+        "bigcup" => "⋃", "bigcap" => "⋂", "bigoplus" => "⨁", "bigotimes" => "⨂",
+        _ => return None,
+    })
+}
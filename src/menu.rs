@@ -0,0 +1,141 @@
+//! Everything related to the site menu
+//!
+//! The menu shown on every page consists of the entries of the configuration and all pages whose
+//! front matter contains `menu: main`. Entries are ordered by their weight and may be nested below
+//! another entry, which is rendered as dropdown. The entry of the current page is highlighted.
+
+use config::MenuConfig;
+use encoding::url_path;
+use renderer::escape_html;
+use template::relative_root;
+
+use std::path::Path;
+
+/// The name of the menu rendered into the layout
+pub const MAIN_MENU: &str = "main";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A single entry of the menu
+pub struct MenuEntry {
+    /// The text of the entry
+    pub title: String,
+    /// The link target, relative to the output directory or absolute
+    pub url: Option<String>,
+    /// The position within the menu, lower weights come first
+    pub weight: i64,
+    /// The entries shown as dropdown below this one
+    pub children: Vec<MenuEntry>,
+}
+
+impl<'a> From<&'a MenuConfig> for MenuEntry {
+    fn from(config: &'a MenuConfig) -> Self {
+        MenuEntry {
+            title: config.title.clone(),
+            url: config.url.clone(),
+            weight: config.weight,
+            children: config.children.iter().map(MenuEntry::from).collect(),
+        }
+    }
+}
+
+impl MenuEntry {
+    /// Returns true if this entry or one of its children links to the page at `current`
+    fn is_active(&self, current: &str) -> bool {
+        self.url.as_ref().is_some_and(|url| url == current) ||
+        self.children.iter().any(|child| child.is_active(current))
+    }
+
+    /// Finds the entry titled `title` within this entry and its children
+    fn find_mut(&mut self, title: &str) -> Option<&mut MenuEntry> {
+        if self.title == title {
+            return Some(self);
+        }
+        self.children.iter_mut().filter_map(|child| child.find_mut(title)).next()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The site menu
+pub struct Menu {
+    /// The top level entries
+    pub entries: Vec<MenuEntry>,
+}
+
+impl Menu {
+    /// Creates the menu of the configured entries
+    pub fn new(config: &[MenuConfig]) -> Self {
+        Menu { entries: config.iter().map(MenuEntry::from).collect() }
+    }
+
+    /// Adds the page at `output` below the entry titled `parent`, or at the top level if there is
+    /// no such entry
+    pub fn add_page(&mut self, title: &str, output: &Path, weight: i64, parent: Option<&str>) {
+        let entry = MenuEntry {
+            title: title.to_owned(),
+            url: Some(url_path(output)),
+            weight,
+            children: Vec::new(),
+        };
+        let parent_entry = parent.and_then(|parent| {
+            let found = self.entries.iter_mut().filter_map(|e| e.find_mut(parent)).next();
+            if found.is_none() {
+                warn!("The menu entry '{}' of '{}' does not exist", parent, output.display());
+            }
+            found
+        });
+        match parent_entry {
+            Some(parent_entry) => parent_entry.children.push(entry),
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Orders all entries by their weight, keeping the order of entries with equal weights
+    pub fn sort(&mut self) {
+        fn sort_entries(entries: &mut [MenuEntry]) {
+            entries.sort_by_key(|entry| entry.weight);
+            for entry in entries {
+                sort_entries(&mut entry.children);
+            }
+        }
+        sort_entries(&mut self.entries);
+    }
+
+    /// Renders the menu as seen from the page at `current`, which is the path of the page
+    /// relative to the output directory. An empty menu renders to nothing.
+    pub fn render(&self, current: &Path) -> String {
+        if self.entries.is_empty() {
+            return String::new();
+        }
+        let mut html = String::from("<nav class=\"menu\">\n<ul>\n");
+        render_entries(&self.entries, &url_path(current), &relative_root(current), &mut html);
+        html.push_str("</ul>\n</nav>");
+        html
+    }
+}
+
+/// Renders `entries` as list items into `html`, relative links are resolved from `root`
+fn render_entries(entries: &[MenuEntry], current: &str, root: &str, html: &mut String) {
+    for entry in entries {
+        if entry.is_active(current) {
+            html.push_str("<li class=\"active\">");
+        } else {
+            html.push_str("<li>");
+        }
+        let title = escape_html(&entry.title);
+        match entry.url {
+            Some(ref url) if url.contains(':') || url.starts_with('/') => {
+                html.push_str(&format!("<a href=\"{}\">{}</a>", escape_html(url), title));
+            }
+            Some(ref url) => {
+                html.push_str(&format!("<a href=\"{}{}\">{}</a>", root, escape_html(url), title));
+            }
+            None => html.push_str(&format!("<span>{}</span>", title)),
+        }
+        if !entry.children.is_empty() {
+            html.push_str("\n<ul class=\"dropdown\">\n");
+            render_entries(&entry.children, current, root, html);
+            html.push_str("</ul>");
+        }
+        html.push_str("</li>\n");
+    }
+}
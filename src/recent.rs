@@ -0,0 +1,123 @@
+//! Everything related to the recent changes page
+//!
+//! The page lists the most recently created or changed pages of the last build, grouped by day.
+//! It is generated from the manifest, which remembers when a page first appeared and when its
+//! content changed, falling back to the modification time of the source file.
+
+use breadcrumbs::Breadcrumbs;
+use chrono::{DateTime, SecondsFormat, Utc};
+use encoding::url_path;
+use filehash::{Manifest, PageEntry};
+use menu::Menu;
+use renderer::escape_html;
+use template::Theme;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file name of the recent changes page within the output directory
+pub const RECENT_FILE: &str = "recent.html";
+
+/// The title of the recent changes page
+pub const RECENT_TITLE: &str = "Recent changes";
+
+#[derive(Clone, Debug, PartialEq)]
+/// A created or changed page
+pub struct Change {
+    /// The title of the page
+    pub title: String,
+    /// The path of the page relative to the output directory
+    pub output: PathBuf,
+    /// The time of the change
+    pub time: DateTime<Utc>,
+    /// The page was created rather than changed
+    pub created: bool,
+    /// The description of the page, if there is one
+    pub description: Option<String>,
+}
+
+impl Change {
+    /// The change of a page stored in the manifest, if its time is known
+    fn from_page(page: &PageEntry) -> Option<Self> {
+        let time = parse_time(&page.changed)
+            .or_else(|| {
+                fs::metadata(&page.source)
+                    .and_then(|metadata| metadata.modified())
+                    .map(DateTime::<Utc>::from)
+                    .ok()
+            })
+            .or_else(|| parse_time(&page.rendered))?;
+        let title = if page.title.is_empty() {
+            page.source.file_stem()?.to_string_lossy().into_owned()
+        } else {
+            page.title.clone()
+        };
+        Some(Change {
+            title,
+            output: page.output.clone(),
+            time,
+            created: !page.created.is_empty() && page.created == page.changed,
+            description: page.description.clone(),
+        })
+    }
+}
+
+/// Parses an RFC 3339 time of the manifest
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time).ok().map(|time| time.with_timezone(&Utc))
+}
+
+/// The `limit` most recent changes of all listed pages within `manifest`, newest first
+pub fn changes(manifest: &Manifest, limit: usize) -> Vec<Change> {
+    let mut changes: Vec<Change> = manifest.pages.iter()
+        .filter(|page| !page.hidden && !page.rendered.is_empty())
+        .filter_map(Change::from_page)
+        .collect();
+    changes.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.output.cmp(&b.output)));
+    changes.truncate(limit);
+    changes
+}
+
+/// Renders the `changes` as HTML fragment grouped by day, linking from the recent changes page
+pub fn render(changes: &[Change]) -> String {
+    let mut html = format!("<h1>{}</h1>\n", RECENT_TITLE);
+    if changes.is_empty() {
+        html.push_str("<p>No changes yet.</p>\n");
+        return html;
+    }
+
+    let mut day = None;
+    for change in changes {
+        let change_day = change.time.date_naive();
+        if day != Some(change_day) {
+            if day.is_some() {
+                html.push_str("</ul>\n");
+            }
+            html.push_str(&format!("<h2>{}</h2>\n<ul class=\"recent\">\n",
+                                   change_day.format("%Y-%m-%d")));
+            day = Some(change_day);
+        }
+        html.push_str(&format!("<li><time datetime=\"{}\">{}</time> <a href=\"{}\">{}</a> {}",
+                               change.time.to_rfc3339_opts(SecondsFormat::Secs, true),
+                               change.time.format("%H:%M"),
+                               escape_html(&url_path(&change.output)),
+                               escape_html(&change.title),
+                               if change.created { "created" } else { "changed" }));
+        if let Some(ref description) = change.description {
+            html.push_str(&format!(": {}", escape_html(description)));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// The recent changes page of the `limit` most recent changes within `manifest`, rendered with
+/// the `theme`
+pub fn page(manifest: &Manifest, limit: usize, theme: &Theme, menu: &Menu,
+            breadcrumbs: &Breadcrumbs) -> String {
+    let output = Path::new(RECENT_FILE);
+    theme.render_page(RECENT_TITLE, &render(&changes(manifest, limit)), output,
+                      &[("menu", &menu.render(output)),
+                        ("breadcrumbs", &breadcrumbs.render(output, RECENT_TITLE))])
+}
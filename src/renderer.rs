@@ -0,0 +1,193 @@
+//! Everything related to turning the content of source files into HTML
+//!
+//! Every source extension is mapped to a renderer by the configuration: markdown files are
+//! converted by the markdown renderer, plain text files are shown preformatted. Relative links to
+//! other source files are rewritten to their HTML outputs. LaTeX math within markdown is
+//! rendered into MathML, diagram code blocks into SVG.
+
+use config::Config;
+use diagram::Diagrams;
+use markdown::to_html;
+use math;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The renderers of source files
+pub enum Renderer {
+    /// Converts markdown into HTML
+    Markdown,
+    /// Shows the escaped plain text preformatted
+    Text,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// The settings every page is rendered with
+pub struct Options<'a> {
+    /// The file extensions of all source files, links to them are rewritten
+    pub extensions: &'a [String],
+    /// Render LaTeX math within `$..$` and `$$..$$` into MathML
+    pub math: bool,
+    /// The tools diagram code blocks are rendered with
+    pub diagrams: &'a Diagrams,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The HTML fragment of a source file
+pub struct Rendered {
+    /// The rendered HTML
+    pub html: String,
+    /// Problems within the source, which did not prevent rendering
+    pub warnings: Vec<String>,
+    /// The cache files of the rendered diagrams
+    pub diagrams: Vec<String>,
+    /// Some diagrams could not be rendered, which may succeed once their tool is available
+    pub diagram_errors: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The markers standing in for parts of a page which are inserted after the markdown is rendered.
+/// They contain a random nonce, so a page can neither contain them by accident nor on purpose.
+pub struct Placeholders {
+    prefix: String,
+}
+
+impl Placeholders {
+    /// Creates the placeholders of the `kind` for the markdown `content`, which does not contain
+    /// any of them
+    pub fn new(kind: &str, content: &str) -> Self {
+        loop {
+            let nonce = RandomState::new().build_hasher().finish();
+            let prefix = format!("wiki{}{:016x}n", kind, nonce);
+            if !content.contains(&prefix) {
+                return Placeholders { prefix };
+            }
+        }
+    }
+
+    /// The placeholder of the entry with `index`, which the markdown renderer keeps as it is
+    pub fn get(&self, index: usize) -> String {
+        format!("{}{}z", self.prefix, index)
+    }
+
+    /// Replaces the placeholder of the entry with `index` within the rendered `html` by
+    /// `replacement`. Placeholders standing in for a `block` replace their paragraph as well.
+    pub fn replace(&self, html: &str, index: usize, replacement: &str, block: bool) -> String {
+        let placeholder = self.get(index);
+        let paragraph = format!("<p>{}</p>", placeholder);
+        if block && html.contains(&paragraph) {
+            html.replacen(&paragraph, replacement, 1)
+        } else {
+            html.replacen(&placeholder, replacement, 1)
+        }
+    }
+}
+
+impl Renderer {
+    /// The renderer of the source file `path`, if its extension is configured
+    pub fn for_path(path: &Path, config: &Config) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if config.markdown.extensions.iter().any(|e| e == extension) {
+            Some(Renderer::Markdown)
+        } else if config.text.extensions.iter().any(|e| e == extension) {
+            Some(Renderer::Text)
+        } else {
+            None
+        }
+    }
+
+    /// Renders the source `content` into an HTML fragment with the given `options`
+    pub fn render(self, content: &str, options: &Options) -> Rendered {
+        match self {
+            Renderer::Markdown => {
+                let diagram_placeholders = Placeholders::new("diagram", content);
+                let math_placeholders = Placeholders::new("math", content);
+                let (content, diagrams) = options.diagrams.extract(content,
+                                                                   &diagram_placeholders);
+                let (content, formulas) = if options.math {
+                    math::extract(&content, &math_placeholders)
+                } else {
+                    (content, Vec::new())
+                };
+                let html = rewrite_links(&to_html(&content), options.extensions);
+                let mut warnings = Vec::new();
+                let html = math::insert(&html, &formulas, &math_placeholders, &mut warnings);
+                let math_warnings = warnings.len();
+                let mut cached = Vec::new();
+                let html = options.diagrams.insert(&html, &diagrams, &diagram_placeholders,
+                                                   &mut warnings, &mut cached);
+                Rendered {
+                    html,
+                    diagram_errors: warnings.len() > math_warnings,
+                    warnings,
+                    diagrams: cached,
+                }
+            }
+            Renderer::Text => Rendered {
+                html: format!("<pre>{}</pre>\n", escape_html(content)),
+                ..Rendered::default()
+            },
+        }
+    }
+}
+
+/// Escapes the characters of `text` which have a special meaning in HTML
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Rewrites all relative links within `html` which point to source files with one of the
+/// `extensions` to the generated HTML files, e.g. `<a href='talks/rayon.md#usage'>` to
+/// `<a href='talks/rayon.html#usage'>`
+pub fn rewrite_links(html: &str, extensions: &[String]) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("href=") {
+        let (before, attribute) = rest.split_at(start + "href=".len());
+        rewritten.push_str(before);
+
+        let quote = match attribute.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => {
+                rest = attribute;
+                continue;
+            }
+        };
+        let end = attribute[1..].find(quote).map_or(attribute.len(), |end| end + 1);
+        let target = &attribute[1..end];
+        rewritten.push(quote);
+        rewritten.push_str(&rewrite_target(target, extensions));
+        rest = &attribute[end..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// Rewrites a single link `target`, keeping anchors and queries
+fn rewrite_target(target: &str, extensions: &[String]) -> String {
+    let split = target.find(['#', '?']).unwrap_or(target.len());
+    let (path, suffix) = target.split_at(split);
+    if path.is_empty() || path.starts_with('/') || path.contains(':') {
+        return target.to_owned();
+    }
+    match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') &&
+                     extensions.iter().any(|e| *e == path[dot + 1..]) => {
+            format!("{}.html{}", &path[..dot], suffix)
+        }
+        _ => target.to_owned(),
+    }
+}
@@ -0,0 +1,135 @@
+//! Everything related to the report of a build
+//!
+//! Every page ends up in the report together with its outcome, errors and warnings, so failing
+//! pages no longer vanish silently.
+
+use encoding::serde_path;
+use error::*;
+use serde_json;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// The outcome of a single page
+pub enum PageStatus {
+    /// The page was rendered
+    Rendered,
+    /// The page and all pages it depends on are unchanged, so rendering was skipped
+    UpToDate,
+    /// The page could not be read or rendered
+    Failed,
+    /// The page was not processed, since the build stopped at the first failure
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+/// The outcome of a single page within a build
+pub struct PageReport {
+    /// The path of the markdown file
+    #[serde(with = "serde_path")]
+    pub source: PathBuf,
+    /// The path of the HTML file relative to the output directory, if there is one
+    #[serde(with = "serde_path::option")]
+    pub output: Option<PathBuf>,
+    /// What happened to the page
+    pub status: PageStatus,
+    /// The reason why the page failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Problems which did not prevent the page from being rendered
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+/// The number of pages per outcome
+pub struct Summary {
+    /// All pages of the build
+    pub total: usize,
+    /// The rendered pages
+    pub rendered: usize,
+    /// The pages which were up to date
+    pub up_to_date: usize,
+    /// The failed pages
+    pub failed: usize,
+    /// The pages which were not processed
+    pub skipped: usize,
+    /// The warnings of all pages
+    pub warnings: usize,
+    /// The copied assets
+    pub assets_copied: usize,
+    /// The assets which could not be copied
+    pub assets_failed: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+/// The outcome of a whole build
+pub struct BuildReport {
+    /// All pages, ordered by their markdown file
+    pub pages: Vec<PageReport>,
+    /// All static assets, ordered by their source file. Rendered assets were copied.
+    pub assets: Vec<PageReport>,
+    /// The number of pages per outcome
+    pub summary: Summary,
+}
+
+impl BuildReport {
+    /// Creates a new report of the given `pages` and `assets`
+    pub fn new(mut pages: Vec<PageReport>, mut assets: Vec<PageReport>) -> Self {
+        pages.sort_by(|a, b| a.source.cmp(&b.source));
+        assets.sort_by(|a, b| a.source.cmp(&b.source));
+
+        let mut summary = Summary {
+            total: pages.len(),
+            ..Summary::default()
+        };
+        for page in &pages {
+            match page.status {
+                PageStatus::Rendered => summary.rendered += 1,
+                PageStatus::UpToDate => summary.up_to_date += 1,
+                PageStatus::Failed => summary.failed += 1,
+                PageStatus::Skipped => summary.skipped += 1,
+            }
+            summary.warnings += page.warnings.len();
+        }
+        for asset in &assets {
+            match asset.status {
+                PageStatus::Rendered => summary.assets_copied += 1,
+                PageStatus::Failed => summary.assets_failed += 1,
+                _ => {}
+            }
+        }
+
+        BuildReport { pages, assets, summary }
+    }
+
+    /// Returns true if any page or asset failed
+    pub fn has_failures(&self) -> bool {
+        self.summary.failed > 0 || self.summary.assets_failed > 0
+    }
+
+    /// The report in JSON format
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Unable to serialize the build report: {}", e).into())
+    }
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for page in self.pages.iter().chain(&self.assets) {
+            if let Some(ref error) = page.error {
+                writeln!(f, "error: {}: {}", page.source.display(), error)?;
+            }
+            for warning in &page.warnings {
+                writeln!(f, "warning: {}: {}", page.source.display(), warning)?;
+            }
+        }
+        let summary = &self.summary;
+        write!(f, "{} pages: {} rendered, {} up to date, {} failed, {} skipped, {} warnings; \
+                   {} assets copied, {} failed",
+               summary.total, summary.rendered, summary.up_to_date, summary.failed,
+               summary.skipped, summary.warnings, summary.assets_copied, summary.assets_failed)
+    }
+}
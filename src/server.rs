@@ -0,0 +1,268 @@
+//! Everything related to the listening sockets of the integrated HTTP server
+
+use error::*;
+use hyper;
+use hyper::net::{HttpListener, NetworkListener, NetworkStream};
+use iron::{Handler, Iron, Protocol};
+
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::PathBuf;
+
+/// The file descriptor of the first socket passed by a socket activating parent
+const LISTEN_FDS_START: i32 = 3;
+
+/// The address the integrated HTTP server listens on
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binding {
+    /// A TCP socket bound to the given host name or IPv4/IPv6 address and port
+    Tcp(String, u16),
+    /// A unix domain socket at the given path, e.g. for reverse proxies
+    Unix(PathBuf),
+    /// An already listening socket inherited from the parent process, following the systemd
+    /// socket activation protocol (`LISTEN_FDS` and `LISTEN_PID`)
+    Inherited,
+}
+
+impl Default for Binding {
+    fn default() -> Self {
+        Binding::Tcp("localhost".to_owned(), 30000)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Binding::Tcp(ref address, port) if address.contains(':') => {
+                write!(f, "[{}]:{}", address, port)
+            }
+            Binding::Tcp(ref address, port) => write!(f, "{}:{}", address, port),
+            Binding::Unix(ref path) => write!(f, "unix:{}", path.display()),
+            Binding::Inherited => write!(f, "inherited socket"),
+        }
+    }
+}
+
+impl Binding {
+    /// Creates a TCP binding, accepting IPv6 addresses with or without surrounding brackets
+    pub fn tcp(address: &str, port: u16) -> Self {
+        let address = address.trim_start_matches('[').trim_end_matches(']');
+        Binding::Tcp(address.to_owned(), port)
+    }
+}
+
+/// Serves the `handler` on the given `binding`, blocking until the server stops
+pub fn listen<H: Handler>(handler: H, binding: &Binding) -> Result<()> {
+    let iron = Iron::new(handler);
+    match *binding {
+        Binding::Tcp(ref address, port) => {
+            let listener = bind_tcp(address, port, binding)?;
+            iron.listen(HttpListener::from(listener), Protocol::http())?;
+        }
+        Binding::Unix(ref path) => {
+            iron.listen(unix::UnixSocketListener::bind(path, binding)?, Protocol::http())?;
+        }
+        Binding::Inherited => match inherited_listener()? {
+            InheritedListener::Tcp(listener) => {
+                iron.listen(HttpListener::from(listener), Protocol::http())?;
+            }
+            #[cfg(unix)]
+            InheritedListener::Unix(listener) => {
+                iron.listen(listener, Protocol::http())?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Binds a TCP listener, trying all addresses the host name resolves to
+fn bind_tcp(address: &str, port: u16, binding: &Binding) -> Result<TcpListener> {
+    let mut last_error = None;
+    let socket_addrs = (address, port).to_socket_addrs()
+        .chain_err(|| ErrorKind::AddressUnresolved(binding.to_string()))?;
+    for socket_addr in socket_addrs {
+        match TcpListener::bind(socket_addr) {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(ref e) if e.kind() == io::ErrorKind::AddrInUse => {
+            bail!(ErrorKind::AddressInUse(binding.to_string()))
+        }
+        Some(e) => Err(e.into()),
+        None => bail!(ErrorKind::AddressUnresolved(binding.to_string())),
+    }
+}
+
+/// A listening socket passed down by the parent process
+enum InheritedListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(unix::UnixSocketListener),
+}
+
+#[cfg(unix)]
+fn inherited_listener() -> Result<InheritedListener> {
+    use std::env;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::os::unix::net::UnixListener;
+    use std::process;
+
+    let listen_pid = env::var("LISTEN_PID").ok().and_then(|p| p.parse::<u32>().ok());
+    let listen_fds = env::var("LISTEN_FDS").ok().and_then(|n| n.parse::<i32>().ok());
+    match (listen_pid, listen_fds) {
+        (Some(pid), Some(fds)) if pid == process::id() && fds > 0 => {
+            if fds > 1 {
+                warn!("Got {} inherited sockets, using only the first one.", fds);
+            }
+        }
+        _ => bail!("No listening socket was inherited (LISTEN_FDS/LISTEN_PID not set for this process)"),
+    }
+
+    // The sockets are owned by this process from now on, so child processes must not adopt them
+    for variable in &["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        env::remove_var(variable);
+    }
+    let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    if listener.local_addr().is_ok() {
+        info!("Using inherited TCP socket {}", listener.local_addr()?);
+        return Ok(InheritedListener::Tcp(listener));
+    }
+
+    let listener = unsafe { UnixListener::from_raw_fd(listener.into_raw_fd()) };
+    if listener.local_addr().is_ok() {
+        info!("Using inherited unix domain socket");
+        return Ok(InheritedListener::Unix(unix::UnixSocketListener::from(listener)));
+    }
+    bail!("The inherited file descriptor {} is neither a TCP nor a unix domain socket",
+          LISTEN_FDS_START)
+}
+
+#[cfg(not(unix))]
+fn inherited_listener() -> Result<InheritedListener> {
+    bail!("Inherited listening sockets are only supported on unix platforms")
+}
+
+/// The placeholder address reported for connections without an IP address
+fn unspecified_addr() -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A `hyper` compatible listener for unix domain sockets
+    #[derive(Clone)]
+    pub struct UnixSocketListener(Arc<UnixListener>);
+
+    impl From<UnixListener> for UnixSocketListener {
+        fn from(listener: UnixListener) -> Self {
+            UnixSocketListener(Arc::new(listener))
+        }
+    }
+
+    impl UnixSocketListener {
+        /// Binds the socket at `path`, replacing a stale socket file from a previous run
+        pub fn bind(path: &Path, binding: &Binding) -> Result<Self> {
+            if path.exists() {
+                if UnixStream::connect(path).is_ok() {
+                    bail!(ErrorKind::AddressInUse(binding.to_string()));
+                }
+                fs::remove_file(path)?;
+            }
+            match UnixListener::bind(path) {
+                Ok(listener) => Ok(UnixSocketListener::from(listener)),
+                Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => {
+                    bail!(ErrorKind::AddressInUse(binding.to_string()))
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+
+    impl NetworkListener for UnixSocketListener {
+        type Stream = UnixSocketStream;
+
+        fn accept(&mut self) -> hyper::Result<UnixSocketStream> {
+            Ok(UnixSocketStream(Arc::new(self.0.accept()?.0)))
+        }
+
+        fn local_addr(&mut self) -> io::Result<SocketAddr> {
+            Ok(unspecified_addr())
+        }
+    }
+
+    /// A single connection accepted on a unix domain socket, shared by its clones
+    #[derive(Clone)]
+    pub struct UnixSocketStream(Arc<UnixStream>);
+
+    impl Read for UnixSocketStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            (&*self.0).read(buf)
+        }
+    }
+
+    impl Write for UnixSocketStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            (&*self.0).write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            (&*self.0).flush()
+        }
+    }
+
+    impl NetworkStream for UnixSocketStream {
+        fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+            Ok(unspecified_addr())
+        }
+
+        fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+            self.0.set_read_timeout(dur)
+        }
+
+        fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+            self.0.set_write_timeout(dur)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod unix {
+    use super::*;
+    use hyper::net::HttpStream;
+    use std::path::Path;
+
+    /// Unix domain sockets are not available on this platform
+    #[derive(Clone)]
+    pub struct UnixSocketListener;
+
+    impl UnixSocketListener {
+        pub fn bind(_: &Path, _: &Binding) -> Result<Self> {
+            bail!("Unix domain sockets are only supported on unix platforms")
+        }
+    }
+
+    impl NetworkListener for UnixSocketListener {
+        type Stream = HttpStream;
+
+        fn accept(&mut self) -> hyper::Result<HttpStream> {
+            Err(io::Error::new(io::ErrorKind::Other,
+                               "Unix domain sockets are only supported on unix platforms").into())
+        }
+
+        fn local_addr(&mut self) -> io::Result<SocketAddr> {
+            Ok(unspecified_addr())
+        }
+    }
+}
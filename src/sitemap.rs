@@ -0,0 +1,61 @@
+//! Everything related to the sitemap and robots.txt for search engines
+//!
+//! The sitemap lists every published page with the date of its last change, drafts and hidden
+//! pages are left out. Since sitemaps need absolute URLs, it is only generated if the `base_url`
+//! is configured.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use config::SitemapConfig;
+use encoding::url_path;
+use renderer::escape_html;
+
+use std::path::PathBuf;
+
+/// The file name of the sitemap within the output directory
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+
+/// The file name of the robots.txt within the output directory
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+#[derive(Clone, Debug, PartialEq)]
+/// A page listed in the sitemap
+pub struct SitemapEntry {
+    /// The path of the page relative to the output directory
+    pub output: PathBuf,
+    /// The date of the last change
+    pub modified: DateTime<Utc>,
+}
+
+/// The absolute URL of `path` relative to the site published at `base_url`
+pub fn absolute_url(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path)
+}
+
+/// The sitemap of all `entries`, ordered by their path
+pub fn sitemap(base_url: &str, entries: &[SitemapEntry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                                <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for entry in entries {
+        xml.push_str(&format!("  <url>\n    <loc>{}</loc>\n    <lastmod>{}</lastmod>\n  </url>\n",
+                              escape_html(&absolute_url(base_url, &url_path(&entry.output))),
+                              entry.modified.to_rfc3339_opts(SecondsFormat::Secs, true)));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// The robots.txt with the disallowed paths of the `config`, referencing the sitemap if there is
+/// a `base_url`
+pub fn robots(config: &SitemapConfig, base_url: &str) -> String {
+    let mut robots = String::from("User-agent: *\n");
+    if config.disallow.is_empty() {
+        robots.push_str("Disallow:\n");
+    }
+    for path in &config.disallow {
+        robots.push_str(&format!("Disallow: {}\n", path));
+    }
+    if config.enabled && !base_url.is_empty() {
+        robots.push_str(&format!("\nSitemap: {}\n", absolute_url(base_url, SITEMAP_FILE)));
+    }
+    robots
+}
@@ -0,0 +1,249 @@
+//! Everything related to the HTML templates and themes of the generated pages
+//!
+//! A theme consists of a `page.html` layout, a `style.css` and a `static` directory, which are
+//! installed into the `theme` directory of the output. Themes are either built in or loaded from
+//! a directory, where missing files fall back to the default theme. The colors, fonts and logo
+//! are theme variables taken from the configuration.
+
+use config::{ColorScheme, Config, StyleConfig};
+use error::*;
+use renderer::escape_html;
+
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+
+/// The built-in layout of every generated page
+pub static PAGE_TEMPLATE: &str = include_str!("themes/default/page.html");
+
+/// The built-in stylesheet of every generated page
+pub static STYLESHEET: &str = include_str!("themes/default/style.css");
+
+/// The directory within the output directory where the theme files are installed
+pub const THEME_OUTPUT: &str = "theme";
+
+/// The built-in themes as name, layout and stylesheet
+static BUILTIN_THEMES: &[(&str, &str, &str)] = &[
+    ("default", PAGE_TEMPLATE, STYLESHEET),
+    ("minimal", include_str!("themes/minimal/page.html"), include_str!("themes/minimal/style.css")),
+];
+
+/// Returns true if `name` is a built-in theme
+pub fn is_builtin_theme(name: &str) -> bool {
+    BUILTIN_THEMES.iter().any(|&(builtin, _, _)| builtin == name)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The theme every page is rendered with
+pub struct Theme {
+    /// The layout every page is rendered into
+    pub layout: String,
+    /// The stylesheet installed as `theme/style.css`
+    pub stylesheet: String,
+    /// The directory of static files copied into the output, if any
+    pub static_directory: Option<PathBuf>,
+    /// The title of the site
+    site_title: String,
+    /// The `<style>` element defining the theme variables
+    variables: String,
+    /// The `color-scheme` meta value
+    color_scheme: &'static str,
+    /// The logo URL relative to the output directory
+    logo: Option<String>,
+}
+
+impl Theme {
+    /// Loads the configured theme, which is the built-in default theme if none is set
+    pub fn load(config: &Config) -> Result<Self> {
+        let name = config.theme.as_deref().unwrap_or("default");
+        let builtin = BUILTIN_THEMES.iter().find(|&&(builtin, _, _)| builtin == name);
+        let (layout, stylesheet, static_directory) = match builtin {
+            Some(&(_, layout, stylesheet)) if !Path::new(name).is_dir() => {
+                (layout.to_owned(), stylesheet.to_owned(), None)
+            }
+            _ => {
+                let directory = Path::new(name);
+                if !directory.is_dir() {
+                    bail!("The theme '{}' is neither built in nor a directory", name);
+                }
+                let static_directory = directory.join("static");
+                (read_or(&directory.join("page.html"), PAGE_TEMPLATE)?,
+                 read_or(&directory.join("style.css"), STYLESHEET)?,
+                 Some(static_directory).filter(|directory| directory.is_dir()))
+            }
+        };
+
+        let style = &config.style;
+        Ok(Theme {
+            layout,
+            stylesheet,
+            static_directory,
+            site_title: config.title.clone(),
+            variables: variables(style),
+            color_scheme: match style.color_scheme {
+                ColorScheme::Auto => "light dark",
+                ColorScheme::Light => "light",
+                ColorScheme::Dark => "dark",
+            },
+            logo: style.logo.clone(),
+        })
+    }
+
+    /// Renders a page with the HTML `content` and `page_title` into the layout, together with
+    /// `navigation` placeholders like the menu. `output` is the path of the page relative to the
+    /// output directory, used for relative links to the theme.
+    pub fn render_page(&self, page_title: &str, content: &str, output: &Path,
+                       navigation: &[(&str, &str)]) -> String {
+        let root = relative_root(output);
+        let logo = self.logo.as_ref().map_or_else(String::new, |logo| {
+            let src = if logo.contains(':') || logo.starts_with('/') {
+                logo.clone()
+            } else {
+                format!("{}{}", root, logo)
+            };
+            format!("<img class=\"logo\" src=\"{}\" alt=\"\">", escape_html(&src))
+        });
+        let site_title = escape_html(&self.site_title);
+        let page_title = escape_html(page_title);
+        let mut variables = vec![("site_title", site_title.as_str()),
+                                 ("page_title", &page_title),
+                                 ("root", &root),
+                                 ("color_scheme", self.color_scheme),
+                                 ("theme_variables", &self.variables),
+                                 ("logo", &logo)];
+        variables.extend_from_slice(navigation);
+        variables.push(("content", content));
+        render(&self.layout, &variables)
+    }
+
+    /// Everything of the theme which influences the generated pages
+    pub fn fingerprint(&self) -> String {
+        [self.layout.as_str(), &self.variables, self.color_scheme,
+         self.logo.as_ref().map_or("", String::as_str)].join("\0")
+    }
+
+    /// Installs the stylesheet and the static files into `output_directory`, writing only files
+    /// which changed
+    pub fn install(&self, output_directory: &Path) -> Result<()> {
+        for (output, source) in self.files()? {
+            let output = output_directory.join(output);
+            match source {
+                Some(source) => {
+                    let content = fs::read(&source)
+                        .chain_err(|| ErrorKind::FileOperation("read".to_owned(), source))?;
+                    write_if_changed(&output, &content)?;
+                }
+                None => write_if_changed(&output, self.stylesheet.as_bytes())?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The files installed into the output directory, relative to it
+    pub fn outputs(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files()?.into_iter().map(|(output, _)| output).collect())
+    }
+
+    /// The installed files relative to the output directory together with the static files they
+    /// are copied from, the stylesheet has none
+    fn files(&self) -> Result<Vec<(PathBuf, Option<PathBuf>)>> {
+        let theme_output = Path::new(THEME_OUTPUT);
+        let mut files = vec![(theme_output.join("style.css"), None)];
+        if let Some(ref directory) = self.static_directory {
+            list_directory(directory, theme_output, &mut files)?;
+        }
+        Ok(files)
+    }
+}
+
+/// The `<style>` element defining the theme variables as CSS custom properties, with the dark
+/// colors applied by the browser preference if the color scheme is automatic
+fn variables(style: &StyleConfig) -> String {
+    let declarations = |background: &str, text: &str| {
+        format!("--accent-color: {}; --background-color: {}; --text-color: {}; \
+                 --font-family: {};",
+                style.accent_color, background, text, style.font_family)
+    };
+    let light = declarations(&style.background_color, &style.text_color);
+    let dark = declarations(&style.dark_background_color, &style.dark_text_color);
+    let rules = match style.color_scheme {
+        ColorScheme::Light => format!(":root {{ {} }}", light),
+        ColorScheme::Dark => format!(":root {{ {} }}", dark),
+        ColorScheme::Auto => {
+            format!(":root {{ {} }}\n@media (prefers-color-scheme: dark) {{ :root {{ {} }} }}",
+                    light, dark)
+        }
+    };
+    format!("<style>\n{}\n</style>", rules.replace('<', "\\3c "))
+}
+
+/// The relative URL of the output directory from the page at `output`, like `../../`
+pub fn relative_root(output: &Path) -> String {
+    "../".repeat(output.components().count().saturating_sub(1))
+}
+
+/// Reads the file at `path` or returns `default` if it does not exist
+fn read_or(path: &Path, default: &str) -> Result<String> {
+    if !path.exists() {
+        return Ok(default.to_owned());
+    }
+    fs::read_to_string(path)
+        .chain_err(|| ErrorKind::FileOperation("read".to_owned(), path.to_path_buf()))
+}
+
+/// Writes `content` to `path` unless the file already has exactly this content
+fn write_if_changed(path: &Path, content: &[u8]) -> Result<()> {
+    if fs::read(path).map(|existing| existing == content).unwrap_or(false) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    debug!("Installing theme file '{}'", path.display());
+    fs::write(path, content)
+        .chain_err(|| ErrorKind::FileOperation("write".to_owned(), path.to_path_buf()))
+}
+
+/// Adds all files within `source` recursively to `files`, as their path within `target` and
+/// their source
+fn list_directory(source: &Path, target: &Path, files: &mut Vec<(PathBuf, Option<PathBuf>)>)
+                  -> Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            list_directory(&path, &target.join(entry.file_name()), files)?;
+        } else {
+            files.push((target.join(entry.file_name()), Some(path)));
+        }
+    }
+    Ok(())
+}
+
+/// Replaces all `{{name}}` placeholders within `template` by their values in a single pass, so
+/// placeholders within the values stay untouched. Unknown placeholders are kept as they are.
+pub fn render(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let value = placeholder[2..].find("}}").and_then(|end| {
+            let name = &placeholder[2..2 + end];
+            variables.iter()
+                     .find(|&&(variable, _)| variable == name)
+                     .map(|&(_, value)| (value, end + 4))
+        });
+        match value {
+            Some((value, length)) => {
+                result.push_str(value);
+                rest = &placeholder[length..];
+            }
+            None => {
+                result.push_str("{{");
+                rest = &placeholder[2..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
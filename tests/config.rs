@@ -0,0 +1,76 @@
+extern crate toml;
+extern crate wikilib;
+
+use wikilib::config::{Config, CONFIG_FILE};
+use wikilib::server::Binding;
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+static CONFIG_DIR: &str = "_tmp_config_dir_";
+
+#[test]
+fn config_precedence() {
+    let config = Config::default();
+    assert_eq!(config.output_directory, Path::new("output"));
+    assert_eq!(config.markdown.extensions, vec!["md", "markdown", "mdown", "mkd"]);
+    assert_eq!(config.text.extensions, vec!["txt"]);
+    assert_eq!(config.server.binding(), Binding::default());
+    assert!(!config.markdown.math);
+    assert!(!config.diagrams.enabled);
+
+    assert!(fs::create_dir_all(CONFIG_DIR).is_ok());
+    let mut file = File::create(Path::new(CONFIG_DIR).join(CONFIG_FILE)).unwrap();
+    assert!(file.write_all(b"title = \"Rust Leipzig\"\n\
+                             output_directory = \"public\"\n\
+                             [server]\n\
+                             port = 8080\n\
+                             [plugins.search]\n\
+                             enabled = true\n").is_ok());
+
+    let config = Config::load(CONFIG_DIR).unwrap();
+    assert_eq!(config.title, "Rust Leipzig");
+    assert_eq!(config.input_directory, Path::new(CONFIG_DIR));
+    assert_eq!(config.output_directory, Path::new(CONFIG_DIR).join("public"));
+    assert_eq!(config.server.binding(), Binding::tcp("localhost", 8080));
+    assert!(config.plugins.contains_key("search"));
+
+    let mut config = Config::load(CONFIG_DIR).unwrap();
+    let variables = vec![("WIKI_TITLE", "From environment"),
+                         ("WIKI_SERVER_PORT", "9090"),
+                         ("TITLE", "Ignored")];
+    assert!(config.apply_variables(variables).is_ok());
+    assert_eq!(config.title, "From environment");
+    assert_eq!(config.server.port, 9090);
+    assert!(config.apply_variables(vec![("WIKI_SERVER_PORT", "http")]).is_err());
+
+    assert!(fs::remove_dir_all(CONFIG_DIR).is_ok());
+}
+
+#[test]
+fn config_unknown_field() {
+    let dir = "_tmp_config_invalid_dir_";
+    assert!(fs::create_dir_all(dir).is_ok());
+    let mut file = File::create(Path::new(dir).join(CONFIG_FILE)).unwrap();
+    assert!(file.write_all(b"unknown_setting = 1\n").is_ok());
+    assert!(Config::load(dir).is_err());
+    assert!(fs::remove_dir_all(dir).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn config_non_utf8_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let directory = OsStr::from_bytes(b"public-\xff");
+    let mut config = Config::default();
+    assert!(config.apply_variables(vec![(OsStr::new("WIKI_OUTPUT_DIRECTORY"), directory)]).is_ok());
+    assert_eq!(config.output_directory.as_os_str(), directory);
+    assert!(config.apply_variables(vec![(OsStr::new("WIKI_TITLE"), directory)]).is_err());
+
+    // Paths are kept losslessly in the serialized configuration
+    let serialized = toml::to_string(&config).unwrap();
+    assert_eq!(toml::from_str::<Config>(&serialized).unwrap(), config);
+}
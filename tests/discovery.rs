@@ -0,0 +1,87 @@
+extern crate wikilib;
+
+use wikilib::config::Config;
+use wikilib::discovery::{find_pages, IGNORE_FILE};
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn write(path: PathBuf, content: &str) {
+    assert!(fs::create_dir_all(path.parent().unwrap()).is_ok());
+    let mut file = File::create(path).unwrap();
+    assert!(file.write_all(content.as_bytes()).is_ok());
+}
+
+#[test]
+fn ignore_rules() {
+    let dir = Path::new("_tmp_discovery_dir_");
+    write(dir.join("index.md"), "# Index");
+    write(dir.join("drafts").join("secret.md"), "# Secret");
+    write(dir.join("talks").join("rayon.md"), "# Rayon");
+    write(dir.join("talks").join("notes.md"), "# Notes");
+    write(dir.join("vendor").join("readme.md"), "# Vendor");
+    write(dir.join(IGNORE_FILE), "drafts/\n");
+    write(dir.join("talks").join(IGNORE_FILE), "notes.md\n");
+    write(dir.join(".gitignore"), "vendor/\n");
+
+    let mut config = Config::default();
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("index.md"),
+                           dir.join("talks").join("rayon.md"),
+                           dir.join("vendor").join("readme.md")]);
+
+    config.discovery.gitignore = true;
+    config.discovery.exclude = vec!["index.md".to_owned()];
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("talks").join("rayon.md")]);
+
+    config.discovery.exclude.clear();
+    config.discovery.include = vec!["index.md".to_owned()];
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("index.md")]);
+
+    // Include patterns never override ignore files
+    config.discovery.include = vec!["drafts/**".to_owned(), "talks/**".to_owned()];
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("talks").join("rayon.md")]);
+
+    assert!(fs::remove_dir_all(dir).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn symbolic_links() {
+    use std::os::unix::fs::symlink;
+
+    let dir = Path::new("_tmp_symlink_dir_");
+    let external = Path::new("_tmp_symlink_external_");
+    write(dir.join("talks").join("rayon.md"), "# Rayon");
+    write(external.join("outside.md"), "# Outside");
+    write(external.join("talks").join("external.md"), "# External");
+    let absolute = |path: &Path| fs::canonicalize(path).unwrap();
+    assert!(symlink(absolute(&dir.join("talks")), dir.join("alias")).is_ok());
+    assert!(symlink(absolute(dir), dir.join("talks").join("loop")).is_ok());
+    assert!(symlink(absolute(&external.join("outside.md")), dir.join("outside.md")).is_ok());
+    assert!(symlink("missing.md", dir.join("dangling.md")).is_ok());
+    assert!(symlink(absolute(&external.join("talks")), dir.join("external")).is_ok());
+
+    // Files reached through the alias and the loop are used once at their real path, external
+    // links are refused
+    let mut config = Config::default();
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("talks").join("rayon.md")]);
+
+    config.discovery.allow_external_links = true;
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("external").join("external.md"),
+                           dir.join("outside.md"),
+                           dir.join("talks").join("rayon.md")]);
+
+    config.discovery.follow_links = false;
+    let pages = find_pages(dir, &config).unwrap();
+    assert_eq!(pages, vec![dir.join("talks").join("rayon.md")]);
+
+    assert!(fs::remove_dir_all(dir).is_ok());
+    assert!(fs::remove_dir_all(external).is_ok());
+}
@@ -0,0 +1,69 @@
+extern crate wikilib;
+
+use wikilib::Wiki;
+use wikilib::encoding::{path_from_url, url_path};
+use wikilib::filehash::Manifest;
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn url_encoding() {
+    assert_eq!(url_path(Path::new("talks/rust & co.html")), "talks/rust%20&%20co.html");
+    assert_eq!(path_from_url(&["talks", "rust%20&%20co.html"]),
+               Some(PathBuf::from("talks/rust & co.html")));
+    assert_eq!(path_from_url(&["talks", "..", "secret"]), None);
+    assert_eq!(path_from_url(&["talks%2F..%2F..", "secret"]), None);
+    assert_eq!(path_from_url::<&str>(&[]), Some(PathBuf::new()));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let input_dir = Path::new("_tmp_non_utf8_input_");
+    let output_dir = Path::new("_tmp_non_utf8_output_");
+    let page = input_dir.join(OsStr::from_bytes(b"caf\xe9.md"));
+    assert!(fs::create_dir_all(input_dir).is_ok());
+    assert!(File::create(&page).unwrap().write_all(b"# Cafe").is_ok());
+    assert!(File::create(input_dir.join("other.md")).unwrap()
+            .write_all(b"[Cafe](caf%E9.md)").is_ok());
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.summary.rendered, 2);
+    assert!(report.to_json().is_ok());
+    assert!(output_dir.join(OsStr::from_bytes(b"caf\xe9.html")).exists());
+
+    // The manifest stores the path losslessly, including the dependency of the other page
+    let manifest = Manifest::load(output_dir.join(".manifest.json"));
+    assert!(manifest.page(&page).is_some());
+    let other = manifest.page(&input_dir.join("other.md")).unwrap();
+    assert_eq!(other.dependencies, vec![page.clone()]);
+
+    assert!(wiki.create_index_tree(output_dir).is_ok());
+    let mut index = String::new();
+    assert!(File::open(output_dir.join("index.html")).unwrap().read_to_string(&mut index).is_ok());
+    assert!(index.contains("href=\"caf%E9.html\""));
+
+    // Generated files are stored losslessly as well, like the static files of a theme
+    let theme_dir = Path::new("_tmp_non_utf8_theme_");
+    let font = OsStr::from_bytes(b"caf\xe9.woff");
+    assert!(fs::create_dir_all(theme_dir.join("static")).is_ok());
+    assert!(File::create(theme_dir.join("static").join(font)).is_ok());
+    let mut config = wiki.config().clone();
+    config.theme = Some(theme_dir.to_string_lossy().into_owned());
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let manifest = Manifest::load(output_dir.join(".manifest.json"));
+    assert!(manifest.generated.contains(&Path::new("theme").join(font)));
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+    assert!(fs::remove_dir_all(theme_dir).is_ok());
+}
@@ -1,8 +1,9 @@
 extern crate wikilib;
 
+use wikilib::Wiki;
 use wikilib::error::*;
-use std::error::Error as StdError;
 use std::io;
+use std::path::{Path, PathBuf};
 
 static ERROR_STR: &str = "Something went wrong here.";
 
@@ -10,6 +11,37 @@ static ERROR_STR: &str = "Something went wrong here.";
 fn io_error_to_wiki_error() {
     let io_error = io::Error::new(io::ErrorKind::NotFound, ERROR_STR);
     let wiki_error: Error = io_error.into();
-    assert_eq!(wiki_error.description(), ERROR_STR.to_string());
+    assert_eq!(wiki_error.to_string(), ERROR_STR.to_string());
 }
 
+
+#[test]
+fn error_kinds_with_context() {
+    let error: Error = ErrorKind::InvalidUtf8(PathBuf::from("page.md"), 2, 5).into();
+    assert_eq!(error.to_string(), "'page.md' is not valid UTF-8 at line 2, column 5");
+    match *error.kind() {
+        ErrorKind::InvalidUtf8(ref path, line, column) => {
+            assert_eq!((path.as_path(), line, column), (Path::new("page.md"), 2, 5))
+        }
+        _ => panic!("Unexpected error kind"),
+    }
+
+    let io_error = io::Error::new(io::ErrorKind::NotFound, ERROR_STR);
+    let error = Err::<(), _>(io_error)
+        .chain_err(|| ErrorKind::FileOperation("read".to_owned(), PathBuf::from("page.md")))
+        .unwrap_err();
+    assert_eq!(full_message(&error), format!("Unable to read 'page.md': {}", ERROR_STR));
+
+    assert_eq!(line_column(b"first\nsecond", 9), (2, 4));
+}
+
+#[test]
+fn input_directory_missing() {
+    let mut wiki = Wiki::new();
+    match *wiki.read_from_directory("_should_not_exist_").unwrap_err().kind() {
+        ErrorKind::InputDirectoryMissing(ref path) => {
+            assert_eq!(path, Path::new("_should_not_exist_"))
+        }
+        _ => panic!("Unexpected error kind"),
+    }
+}
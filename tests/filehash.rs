@@ -0,0 +1,94 @@
+extern crate wikilib;
+
+use wikilib::Wiki;
+use wikilib::filehash::{Filehash, Manifest, LEGACY_SHA_FILE, MANIFEST_FILE, MANIFEST_VERSION};
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn sha256_file_hash() {
+    assert_eq!(Filehash::hash(b"abc"),
+               "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+}
+
+#[test]
+fn migrate_legacy_sha_file() {
+    let input_dir = "_tmp_migrate_input_";
+    let output_dir = "_tmp_migrate_output_";
+    assert!(fs::create_dir_all(input_dir).is_ok());
+    assert!(fs::create_dir_all(output_dir).is_ok());
+    let page = Path::new(input_dir).join("page:with:colons.md");
+    assert!(File::create(&page).unwrap().write_all(b"# Colons\n").is_ok());
+
+    // A legacy hash file with an output of a page which does not exist anymore
+    let mut legacy = File::create(Path::new(output_dir).join(LEGACY_SHA_FILE)).unwrap();
+    assert!(legacy.write_all(b"80179b92-2f60-5dae-881e-3295fd718dac:gone.md:gone.html\n").is_ok());
+    assert!(File::create(Path::new(output_dir).join("gone.html")).is_ok());
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(!Path::new(output_dir).join(LEGACY_SHA_FILE).exists());
+    assert!(!Path::new(output_dir).join("gone.html").exists());
+
+    let manifest_file = Path::new(output_dir).join(MANIFEST_FILE);
+    let manifest = Manifest::load(&manifest_file);
+    assert_eq!(manifest.version, MANIFEST_VERSION);
+    assert_eq!(manifest.pages.len(), 1);
+    let entry = manifest.page(&page).unwrap();
+    assert_eq!(entry.output, PathBuf::from("page:with:colons.html"));
+    assert_eq!(entry.hash, Filehash::hash(b"# Colons\n"));
+    assert_eq!(entry.size, 9);
+    assert!(!entry.rendered.is_empty());
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn cache_invalidation() {
+    let input_dir = "_tmp_cache_input_";
+    let output_dir = "_tmp_cache_output_";
+    assert!(fs::create_dir_all(input_dir).is_ok());
+    assert!(File::create(Path::new(input_dir).join("page.md")).unwrap().write_all(b"# A\n").is_ok());
+    let manifest_file = Path::new(output_dir).join(MANIFEST_FILE);
+    let rendered = || {
+        let manifest = Manifest::load(&manifest_file);
+        manifest.pages[0].rendered.clone()
+    };
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let first = rendered();
+
+    // Nothing changed
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert_eq!(rendered(), first);
+
+    // The output was deleted
+    assert!(fs::remove_file(Path::new(output_dir).join("page.html")).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let second = rendered();
+    assert!(second != first);
+    assert!(Path::new(output_dir).join("page.html").exists());
+
+    // The rendering settings changed
+    let mut config = wiki.config().clone();
+    config.title = "Another title".to_owned();
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let third = rendered();
+    assert!(third != second);
+
+    // A forced rebuild
+    wiki.set_force_rebuild(true);
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(rendered() != third);
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
@@ -4,20 +4,78 @@ extern crate glob;
 
 use log::LogLevel;
 use wikilib::Wiki;
+use wikilib::error::ErrorKind;
+use wikilib::feed;
+use wikilib::config::{Config, MenuConfig, MountConfig};
+use wikilib::filehash::{Manifest, PageEntry};
+use wikilib::report::PageStatus;
+use wikilib::template;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::{Read, Write};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
 
 static NON_EXISTING_DIR: &str = "_should_not_exist_";
 static TMP_DIR: &str = "_tmp_dir_";
 
+/// The input and output directory of a test, which are removed afterwards
+struct TestDirs {
+    input: PathBuf,
+    output: PathBuf,
+}
+
+impl TestDirs {
+    /// Creates the empty input directory `_tmp_<name>_input_`, the output directory
+    /// `_tmp_<name>_output_` is created by the build
+    fn new(name: &str) -> Self {
+        let dirs = TestDirs {
+            input: PathBuf::from(format!("_tmp_{}_input_", name)),
+            output: PathBuf::from(format!("_tmp_{}_output_", name)),
+        };
+        dirs.remove();
+        assert!(fs::create_dir_all(&dirs.input).is_ok());
+        dirs
+    }
+
+    /// Writes `content` into the input file `name`, creating its directories
+    fn write(&self, name: &str, content: &str) {
+        let path = self.input.join(name);
+        assert!(fs::create_dir_all(path.parent().unwrap()).is_ok());
+        let mut file = fs::File::create(path).unwrap();
+        assert!(file.write_all(content.as_bytes()).is_ok());
+    }
+
+    /// Reads the output file `name`
+    fn read(&self, name: &str) -> String {
+        let mut content = String::new();
+        assert!(fs::File::open(self.output.join(name)).unwrap()
+                .read_to_string(&mut content).is_ok());
+        content
+    }
+
+    /// Removes both directories, including the leftovers of failed runs
+    fn remove(&self) {
+        let _ = fs::remove_dir_all(&self.input);
+        let _ = fs::remove_dir_all(&self.output);
+    }
+}
+
+impl Drop for TestDirs {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
 #[test]
 fn test_read_from_directory() {
     let mut wiki = Wiki::new();
     assert!(wiki.init_logging(LogLevel::Trace).is_ok());
     let input_dir = "tests/example_md/real_md";
     assert!(wiki.read_from_directory(input_dir).is_ok());
-    let sha_file = Path::new("html").join(".files.sha");
+    let sha_file = Path::new("html").join(".manifest.json");
     if sha_file.exists() {
         assert!(fs::remove_file(&sha_file).is_ok());
     }
@@ -51,7 +109,7 @@ fn test_sha_file_existing() {
     let input_dir = "tests/example_md/real_md";
     assert!(wiki.read_from_directory(input_dir).is_ok());
     assert!(wiki.read_content_from_current_paths(input_dir, "html2").is_ok());
-    let sha_file = Path::new("html2").join(".files.sha");
+    let sha_file = Path::new("html2").join(".manifest.json");
     assert!(sha_file.exists());
     assert!(wiki.read_content_from_current_paths(input_dir, "html2").is_ok());
 }
@@ -62,6 +120,20 @@ fn test_read_from_non_existing_directory() {
     assert!(wiki.read_from_directory("_non-exisiting_").is_err())
 }
 
+#[test]
+fn test_read_content_from_another_directory() {
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory("tests/example_md/real_md").is_ok());
+    match *wiki.read_content_from_current_paths("tests/example_md", "html3").unwrap_err().kind() {
+        ErrorKind::InputDirectoryMismatch(ref given, ref read) => {
+            assert_eq!((given.as_path(), read.as_path()),
+                       (Path::new("tests/example_md"), Path::new("tests/example_md/real_md")))
+        }
+        _ => panic!("Unexpected error kind"),
+    }
+    assert!(!Path::new("html3").exists());
+}
+
 #[test]
 fn test_read_non_existing_content() {
     let mut wiki = Wiki::new();
@@ -74,3 +146,758 @@ fn test_read_non_existing_content() {
         Err(_) => assert!(!Path::new(NON_EXISTING_DIR).exists())
     }
 }
+
+#[test]
+fn test_read_assets_only() {
+    let dirs = TestDirs::new("assets_only");
+    dirs.write("logo.png", "");
+    dirs.write("notes/draft.rst", "");
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let error = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap_err();
+    match *error.kind() {
+        ErrorKind::NoInputFiles(ref path) => assert_eq!(path, &dirs.input),
+        _ => panic!("Unexpected error kind"),
+    }
+    assert_eq!(error.to_string(), "No pages found in '_tmp_assets_only_input_': no file matched \
+                                   the discovery patterns");
+    assert!(!dirs.output.exists());
+}
+
+#[test]
+fn test_init_new_and_clean() {
+    let wiki_dir = "_tmp_init_dir_";
+    let output_dir = Path::new(wiki_dir).join("output");
+    let output_dir = output_dir.as_path();
+    assert!(Wiki::init_directory(wiki_dir).is_ok());
+    assert!(Wiki::init_directory(wiki_dir).is_err());
+    assert!(Path::new(wiki_dir).join("wiki.toml").exists());
+    assert!(Path::new(wiki_dir).join("theme").join("page.html").exists());
+
+    let mut wiki = Wiki::new();
+    let page = wiki.new_page(wiki_dir, "talks/rayon").unwrap();
+    assert_eq!(page, Path::new(wiki_dir).join("talks").join("rayon.md"));
+    assert!(wiki.new_page(wiki_dir, "talks/rayon.md").is_err());
+
+    assert!(wiki.read_from_directory(wiki_dir).is_ok());
+    assert!(wiki.check_current_paths().is_ok());
+    assert!(wiki.read_content_from_current_paths(wiki_dir, output_dir).is_ok());
+    assert!(Path::new(output_dir).join("talks").join("rayon.html").exists());
+
+    assert!(wiki.clean(wiki_dir, wiki_dir).is_err());
+    assert!(wiki.clean(wiki_dir, output_dir).is_ok());
+    assert!(!Path::new(output_dir).exists());
+    assert!(fs::remove_dir_all(wiki_dir).is_ok());
+}
+
+#[test]
+fn test_remove_stale_outputs() {
+    let input_dir = "_tmp_stale_input_";
+    let output_dir = "_tmp_stale_output_";
+    let page_dir = Path::new(input_dir).join("section");
+    assert!(fs::create_dir_all(&page_dir).is_ok());
+    assert!(fs::File::create(page_dir.join("old.md")).is_ok());
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let stale_file = Path::new(output_dir).join("section").join("old.html");
+    assert!(stale_file.exists());
+
+    assert!(fs::rename(page_dir.join("old.md"), Path::new(input_dir).join("new.md")).is_ok());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let stale_outputs = wiki.remove_stale_outputs(output_dir, true).unwrap();
+    assert_eq!(stale_outputs, vec![Path::new("section").join("old.html")]);
+    assert!(stale_file.exists());
+
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(!stale_file.exists());
+    assert!(!Path::new(output_dir).join("section").exists());
+    assert!(Path::new(output_dir).join("new.html").exists());
+
+    // Outputs of a tampered manifest outside of the output directory are never removed
+    let outside = "_tmp_stale_outside_.html";
+    assert!(fs::File::create(outside).is_ok());
+    let manifest_file = Path::new(output_dir).join(".manifest.json");
+    let mut manifest = Manifest::load(&manifest_file);
+    manifest.assets.push(PageEntry {
+        source: PathBuf::from("removed.html"),
+        output: Path::new("..").join(outside),
+        ..PageEntry::default()
+    });
+    assert!(manifest.save(&manifest_file).is_ok());
+    assert_eq!(wiki.remove_stale_outputs(output_dir, false).unwrap(), Vec::<PathBuf>::new());
+    assert!(Path::new(outside).exists());
+    assert!(fs::remove_file(outside).is_ok());
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_dependency_rebuild() {
+    let input_dir = "_tmp_dependency_input_";
+    let output_dir = "_tmp_dependency_output_";
+    assert!(fs::create_dir_all(Path::new(input_dir).join("talks")).is_ok());
+    let write = |name: &str, content: &str| {
+        let mut file = fs::File::create(Path::new(input_dir).join(name)).unwrap();
+        assert!(file.write_all(content.as_bytes()).is_ok());
+    };
+    write("index.md", "See [the talk](talks/rayon.md)\n");
+    write("other.md", "# Unrelated\n");
+    write("talks/rayon.md", "# Rayon\n");
+
+    let manifest_file = Path::new(output_dir).join(".manifest.json");
+    let rendered = |source: &str| {
+        let manifest = Manifest::load(&manifest_file);
+        manifest.page(&Path::new(input_dir).join(source)).unwrap().rendered.clone()
+    };
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let (index, other) = (rendered("index.md"), rendered("other.md"));
+
+    // Changing the linked page re-renders the linking page only
+    thread::sleep(Duration::from_millis(10));
+    write("talks/rayon.md", "# Rayon, data parallelism\n");
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(rendered("index.md") != index);
+    assert_eq!(rendered("other.md"), other);
+
+    // Renaming the linked page re-renders the linking page as well
+    let index = rendered("index.md");
+    thread::sleep(Duration::from_millis(10));
+    assert!(fs::rename(Path::new(input_dir).join("talks/rayon.md"),
+                       Path::new(input_dir).join("talks/tokio.md")).is_ok());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(rendered("index.md") != index);
+    assert_eq!(rendered("other.md"), other);
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_build_report() {
+    let input_dir = "_tmp_report_input_";
+    let output_dir = "_tmp_report_output_";
+    assert!(fs::create_dir_all(input_dir).is_ok());
+    let mut file = fs::File::create(Path::new(input_dir).join("valid.md")).unwrap();
+    assert!(file.write_all(b"See [the missing page](missing.md)\n").is_ok());
+    let mut file = fs::File::create(Path::new(input_dir).join("invalid.md")).unwrap();
+    assert!(file.write_all(&[0xff, 0xfe, 0xfd]).is_ok());
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert!(report.has_failures());
+    assert_eq!((report.summary.total, report.summary.rendered, report.summary.failed),
+               (2, 1, 1));
+    assert_eq!(report.pages[0].status, PageStatus::Failed);
+    assert!(report.pages[0].error.as_ref().unwrap().starts_with("Unable to render"));
+    assert_eq!(report.pages[1].status, PageStatus::Rendered);
+    assert_eq!(report.pages[1].warnings.len(), 1);
+    assert!(report.to_json().unwrap().contains("\"failed\": 1"));
+
+    // A page skipped when failing fast is rendered again, even if it was up to date
+    wiki.set_fail_fast(true);
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.pages[1].status, PageStatus::Skipped);
+    let manifest = Manifest::load(Path::new(output_dir).join(".manifest.json"));
+    assert!(manifest.pages.iter().all(|page| page.cache_key.is_empty()));
+    wiki.set_fail_fast(false);
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.pages[1].status, PageStatus::Rendered);
+
+    // The failed page is rendered again, the other one is skipped when failing fast
+    wiki.set_fail_fast(true);
+    wiki.set_force_rebuild(true);
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.pages[0].status, PageStatus::Failed);
+    assert_eq!(report.pages[1].status, PageStatus::Skipped);
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_source_extensions() {
+    let input_dir = "_tmp_extensions_input_";
+    let output_dir = "_tmp_extensions_output_";
+    assert!(fs::create_dir_all(Path::new(input_dir).join("docs.md")).is_ok());
+    let write = |name: &str, content: &str| {
+        let mut file = fs::File::create(Path::new(input_dir).join(name)).unwrap();
+        assert!(file.write_all(content.as_bytes()).is_ok());
+    };
+    write("guide.markdown", "# Guide\n");
+    write("docs.md/page.mkd", "# Page\n");
+    write("release.md.notes.txt", "<b>not bold</b>\n");
+    write("image.png", "");
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.summary.rendered, 3);
+
+    let output = Path::new(output_dir);
+    assert!(output.join("guide.html").exists());
+    assert!(output.join("docs.md").join("page.html").exists());
+    let mut text = String::new();
+    assert!(fs::File::open(output.join("release.md.notes.html")).unwrap()
+            .read_to_string(&mut text).is_ok());
+    assert!(text.contains("&lt;b&gt;not bold&lt;/b&gt;"));
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_mounts() {
+    let input_dir = "_tmp_mount_input_";
+    let mounted_dir = "_tmp_mount_mounted_";
+    let output_dir = "_tmp_mount_output_";
+    assert!(fs::create_dir_all(input_dir).is_ok());
+    assert!(fs::create_dir_all(mounted_dir).is_ok());
+    assert!(fs::File::create(Path::new(input_dir).join("about.md")).is_ok());
+    assert!(fs::File::create(Path::new(mounted_dir).join("rayon.md")).is_ok());
+
+    let mut config = Config::default();
+    config.mounts.push(MountConfig {
+        directory: PathBuf::from(mounted_dir),
+        prefix: "talks/2017".to_owned(),
+    });
+    let mut wiki = Wiki::with_config(config.clone());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(wiki.create_index_tree(output_dir).is_ok());
+    let output = Path::new(output_dir);
+    assert!(output.join("about.html").exists());
+    assert!(output.join("talks").join("2017").join("rayon.html").exists());
+    let mut index = String::new();
+    assert!(fs::File::open(output.join("index.html")).unwrap()
+            .read_to_string(&mut index).is_ok());
+    assert!(index.contains("href=\"talks/2017/rayon.html\""));
+
+    // Added pages are listed after the next build
+    assert!(fs::File::create(Path::new(input_dir).join("news.md")).is_ok());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(wiki.create_index_tree(output_dir).is_ok());
+    let mut index = String::new();
+    assert!(fs::File::open(output.join("index.html")).unwrap()
+            .read_to_string(&mut index).is_ok());
+    assert!(index.contains("href=\"news.html\""));
+
+    // A page of the input directory at the same path conflicts with the mounted one
+    assert!(fs::create_dir_all(Path::new(input_dir).join("talks").join("2017")).is_ok());
+    assert!(fs::File::create(Path::new(input_dir).join("talks/2017/rayon.md")).is_ok());
+    match wiki.read_from_directory(input_dir).map_err(|e| e.kind().to_string()) {
+        Err(message) => assert!(message.contains("talks/2017/rayon.html")),
+        Ok(_) => panic!("Conflicting pages were accepted"),
+    }
+
+    config.mounts[0].prefix = "../outside".to_owned();
+    assert!(Wiki::with_config(config).read_from_directory(input_dir).is_err());
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(mounted_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_assets_and_links() {
+    let input_dir = "_tmp_assets_input_";
+    let output_dir = "_tmp_assets_output_";
+    let input = Path::new(input_dir);
+    let output = Path::new(output_dir);
+    assert!(fs::create_dir_all(input.join("images")).is_ok());
+    let write = |name: &str, content: &str| {
+        let mut file = fs::File::create(input.join(name)).unwrap();
+        assert!(file.write_all(content.as_bytes()).is_ok());
+    };
+    write("index.md", "[Rayon](talks/rayon.md#usage) and [Rust](https://www.rust-lang.org/x.md)\n");
+    write("images/logo.png", "logo");
+    write(".hidden", "secret");
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.summary.assets_copied, 1);
+    assert!(output.join("images").join("logo.png").exists());
+    assert!(!output.join(".hidden").exists());
+    let mut index = String::new();
+    assert!(fs::File::open(output.join("index.html")).unwrap()
+            .read_to_string(&mut index).is_ok());
+    assert!(index.contains("href='talks/rayon.html#usage'"));
+    assert!(index.contains("href='https://www.rust-lang.org/x.md'"));
+
+    // Unchanged assets are not copied again, changed ones are
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.summary.assets_copied, 0);
+    write("images/logo.png", "new logo");
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.summary.assets_copied, 1);
+
+    // Removed assets are removed from the output as well
+    assert!(fs::remove_file(input.join("images").join("logo.png")).is_ok());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(!output.join("images").exists());
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_output_within_input() {
+    let input_dir = "_tmp_nested_input_";
+    let output_dir = Path::new(input_dir).join("site");
+    assert!(fs::create_dir_all(input_dir).is_ok());
+    let mut file = fs::File::create(Path::new(input_dir).join("page.md")).unwrap();
+    assert!(file.write_all(b"# Page\n").is_ok());
+
+    // The generated files are never read as assets, although another output directory is
+    // configured
+    let mut wiki = Wiki::new();
+    for _ in 0..2 {
+        assert!(wiki.read_from_directory(input_dir).is_ok());
+        let report = wiki.read_content_from_current_paths(input_dir, &output_dir).unwrap();
+        assert_eq!((report.summary.total, report.summary.assets_copied), (1, 0));
+    }
+    assert!(!output_dir.join("site").exists());
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+}
+
+#[test]
+fn test_themes() {
+    let input_dir = "_tmp_theme_input_";
+    let theme_dir = "_tmp_theme_theme_";
+    let output_dir = "_tmp_theme_output_";
+    assert!(fs::create_dir_all(Path::new(input_dir).join("talks")).is_ok());
+    assert!(fs::create_dir_all(Path::new(theme_dir).join("static").join("fonts")).is_ok());
+    assert!(fs::File::create(Path::new(input_dir).join("talks").join("rayon.md")).is_ok());
+    let read = |path: &Path| {
+        let mut content = String::new();
+        assert!(fs::File::open(path).unwrap().read_to_string(&mut content).is_ok());
+        content
+    };
+    let output = Path::new(output_dir);
+
+    // The built-in themes are installed with the theme variables of the configuration
+    let mut config = Config::default();
+    config.theme = Some("minimal".to_owned());
+    config.style.accent_color = "#ff0000".to_owned();
+    config.style.logo = Some("files/logo.svg".to_owned());
+    let mut wiki = Wiki::with_config(config.clone());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let page = read(&output.join("talks").join("rayon.html"));
+    assert!(page.contains("href=\"../theme/style.css\""));
+    assert!(page.contains("--accent-color: #ff0000;"));
+    assert!(page.contains("@media (prefers-color-scheme: dark)"));
+    assert!(output.join("theme").join("style.css").exists());
+
+    // A theme directory replaces the layout and brings its static files
+    let mut layout = fs::File::create(Path::new(theme_dir).join("page.html")).unwrap();
+    assert!(layout.write_all(b"<main>{{logo}}{{content}}</main>").is_ok());
+    assert!(fs::File::create(Path::new(theme_dir).join("static/fonts/sans.woff")).is_ok());
+    config.theme = Some(theme_dir.to_owned());
+    let mut wiki = Wiki::with_config(config.clone());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    let report = wiki.read_content_from_current_paths(input_dir, output_dir).unwrap();
+    assert_eq!(report.summary.rendered, 1);
+    assert_eq!(read(&output.join("talks").join("rayon.html")),
+               "<main><img class=\"logo\" src=\"../files/logo.svg\" alt=\"\">\n</main>");
+    assert!(output.join("theme").join("fonts").join("sans.woff").exists());
+
+    // Placeholders are only replaced within the layout, never within the values
+    assert_eq!(template::render("{{title}}: {{content}} {{unknown}} {{open",
+                                &[("title", "{{content}}"), ("content", "{{title}}")]),
+               "{{content}}: {{title}} {{unknown}} {{open");
+
+    // Static files removed from the theme are removed from the output as well
+    assert!(fs::remove_dir_all(Path::new(theme_dir).join("static").join("fonts")).is_ok());
+    let mut wiki = Wiki::with_config(config.clone());
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    assert!(!output.join("theme").join("fonts").exists());
+    assert!(output.join("theme").join("style.css").exists());
+
+    config.theme = Some("missing".to_owned());
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_err());
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(theme_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_menu() {
+    let dirs = TestDirs::new("menu");
+    dirs.write("about.md", "---\ntitle: About us\nmenu: main\nweight: 20\n---\n# About\n");
+    dirs.write("talks/rayon.md", "---\nmenu: main\nparent: Talks\n---\n# Rayon\n");
+    dirs.write("other.md", "# Not in the menu\n");
+
+    let mut config = Config::default();
+    config.menu.push(MenuConfig {
+        title: "Talks".to_owned(),
+        url: None,
+        weight: 10,
+        children: Vec::new(),
+    });
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    assert_eq!(report.summary.rendered, 3);
+
+    let about = dirs.read("about.html");
+    assert!(about.contains("<title>About us - Wiki</title>"));
+    assert!(!about.contains("menu: main"));
+    assert!(about.contains("<li><span>Talks</span>\n<ul class=\"dropdown\">\n\
+                            <li><a href=\"talks/rayon.html\">rayon</a></li>\n</ul></li>\n\
+                            <li class=\"active\"><a href=\"about.html\">About us</a></li>"));
+    let rayon = dirs.read("talks/rayon.html");
+    assert!(rayon.contains("<li class=\"active\"><span>Talks</span>"));
+    assert!(rayon.contains("<a href=\"../about.html\">About us</a>"));
+
+    // Changing a menu entry rebuilds every page, invalid front matter fails the page
+    dirs.write("about.md", "---\ntitle: About\nmenu: main\n---\n# About\n");
+    dirs.write("other.md", "---\nweight: heavy\n---\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    assert_eq!(report.summary.rendered, 2);
+    assert_eq!(report.summary.failed, 1);
+    assert!(dirs.read("talks/rayon.html").contains(">About</a>"));
+}
+
+#[test]
+fn test_breadcrumbs() {
+    let input_dir = "_tmp_breadcrumbs_input_";
+    let output_dir = "_tmp_breadcrumbs_output_";
+    let input = Path::new(input_dir);
+    assert!(fs::create_dir_all(input.join("talks").join("2017")).is_ok());
+    let write = |name: &str, content: &str| {
+        let mut file = fs::File::create(input.join(name)).unwrap();
+        assert!(file.write_all(content.as_bytes()).is_ok());
+    };
+    write("index.md", "# Home\n");
+    write("talks/index.md", "---\ntitle: All talks\n---\n");
+    write("talks/2017/rayon.md", "# Rayon\n");
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(input_dir).is_ok());
+    assert!(wiki.read_content_from_current_paths(input_dir, output_dir).is_ok());
+    let mut rayon = String::new();
+    assert!(fs::File::open(Path::new(output_dir).join("talks/2017/rayon.html")).unwrap()
+            .read_to_string(&mut rayon).is_ok());
+    assert!(rayon.contains("<li><a href=\"../../index.html\">Wiki</a></li>\n\
+                            <li><a href=\"../../talks/index.html\">All talks</a></li>\n\
+                            <li><span>2017</span></li>\n\
+                            <li aria-current=\"page\">rayon</li>"));
+    let mut index = String::new();
+    assert!(fs::File::open(Path::new(output_dir).join("index.html")).unwrap()
+            .read_to_string(&mut index).is_ok());
+    assert!(!index.contains("breadcrumbs"));
+
+    assert!(fs::remove_dir_all(input_dir).is_ok());
+    assert!(fs::remove_dir_all(output_dir).is_ok());
+}
+
+#[test]
+fn test_sitemap() {
+    let dirs = TestDirs::new("sitemap");
+    dirs.write("talks/rayon.md", "---\ndate: 2017-05-04\n---\n# Rayon\n");
+    dirs.write("draft.md", "---\ndraft: true\nmenu: main\n---\n");
+    dirs.write("secret.md", "---\nhidden: true\n---\n");
+
+    let mut config = Config::default();
+    config.base_url = "https://example.org/wiki/".to_owned();
+    config.sitemap.disallow.push("/drafts/".to_owned());
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    assert_eq!(dirs.read("sitemap.xml"),
+               "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  \
+                <url>\n    <loc>https://example.org/wiki/index.html</loc>\n    \
+                <lastmod>2017-05-04T00:00:00Z</lastmod>\n  </url>\n  \
+                <url>\n    <loc>https://example.org/wiki/talks/rayon.html</loc>\n    \
+                <lastmod>2017-05-04T00:00:00Z</lastmod>\n  </url>\n</urlset>\n");
+    assert_eq!(dirs.read("robots.txt"),
+               "User-agent: *\nDisallow: /drafts/\n\nSitemap: https://example.org/wiki/sitemap.xml\n");
+
+    // Drafts are rendered, but neither listed in the index nor the menu
+    assert!(dirs.output.join("draft.html").exists());
+    let index = dirs.read("index.html");
+    assert!(index.contains("secret.html") && !index.contains("draft.html"));
+    assert!(!dirs.read("talks/rayon.html").contains("draft.html"));
+
+    // A sitemap within the input directory is copied instead
+    dirs.write("sitemap.xml", "<urlset/>\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    assert_eq!(dirs.read("sitemap.xml"), "<urlset/>\n");
+}
+
+#[test]
+fn test_feeds() {
+    let dirs = TestDirs::new("feeds");
+    dirs.write("talks/rayon.md",
+               "---\ndate: 2017-05-04\n---\n# Rayon\n\nData *parallelism* & more.\n");
+    dirs.write("talks/serde.md", "---\ndate: 2017-06-01\ndescription: Serialization\n---\n");
+    dirs.write("meetings.md", "---\ndate: 2017-07-01\n---\nEvery thursday\n");
+    dirs.write("draft.md", "---\ndraft: true\n---\n");
+    dirs.write("talks/rss.xml", "<rss/>");
+
+    let mut config = Config::default();
+    config.base_url = "https://example.org".to_owned();
+    config.feeds.sections.push("talks".to_owned());
+    config.feeds.limit = 2;
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+
+    let atom = dirs.read("atom.xml");
+    assert!(atom.contains("<id>https://example.org/atom.xml</id>"));
+    assert!(atom.contains("<updated>2017-07-01T00:00:00Z</updated>"));
+    assert!(atom.find("meetings.html").unwrap() < atom.find("serde.html").unwrap());
+    assert!(!atom.contains("rayon.html") && !atom.contains("draft.html"));
+    assert!(dirs.read("rss.xml").contains("<pubDate>Sat, 1 Jul 2017 00:00:00 +0000</pubDate>"));
+
+    let talks = dirs.read("talks/feed.json");
+    assert!(talks.contains("\"feed_url\": \"https://example.org/talks/feed.json\""));
+    assert!(talks.contains("\"summary\": \"Serialization\""));
+    assert!(talks.contains("\"summary\": \"Data parallelism & more.\""));
+    assert!(!talks.contains("meetings.html"));
+
+    // Feeds provided as assets are kept
+    assert_eq!(dirs.read("talks/rss.xml"), "<rss/>");
+    assert!(dirs.read("talks/atom.xml").contains("serde.html"));
+}
+
+#[test]
+fn test_git_dates() {
+    let dirs = TestDirs::new("git_dates");
+    dirs.write("talks/über \"rayon\".md", "# Rayon\n");
+    dirs.write("serde.md", "# Serde\n");
+    let git = |args: &[&str]| {
+        Command::new("git").arg("-C").arg(&dirs.input)
+                           .args(["-c", "user.name=wiki", "-c", "user.email=wiki@example.org"])
+                           .args(args)
+                           .env("GIT_COMMITTER_DATE", "2017-05-04T12:00:00Z")
+                           .status()
+                           .map(|status| status.success())
+                           .unwrap_or(false)
+    };
+    if !git(&["init", "-q"]) {
+        // Without git, no dates are known
+        return;
+    }
+    assert!(git(&["add", "."]) && git(&["commit", "-q", "-m", "Talks"]));
+
+    // Unusual file names are not quoted
+    let dates = feed::git_dates(&dirs.input);
+    assert_eq!(dates.len(), 2);
+    assert_eq!(dates[&PathBuf::from("talks/über \"rayon\".md")].to_rfc3339(),
+               "2017-05-04T12:00:00+00:00");
+    assert!(dates.contains_key(&PathBuf::from("serde.md")));
+}
+
+#[test]
+fn test_recent_changes() {
+    let dirs = TestDirs::new("recent");
+    dirs.write("rayon.md", "---\ntitle: Rayon\n---\n");
+    dirs.write("serde.md", "---\ndescription: Serialization\n---\n");
+    dirs.write("secret.md", "---\nhidden: true\n---\n");
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    let recent = dirs.read("recent.html");
+    assert!(recent.contains("<a href=\"rayon.html\">Rayon</a> created</li>"));
+    assert!(recent.contains("<a href=\"serde.html\">serde</a> created: Serialization</li>"));
+    assert!(!recent.contains("secret.html"));
+
+    // Changed pages come first, unchanged pages keep the time of the manifest
+    let manifest_file = dirs.output.join(".manifest.json");
+    let mut manifest = Manifest::load(&manifest_file);
+    for page in &mut manifest.pages {
+        page.created = "2017-05-04T00:00:00+00:00".to_owned();
+        page.changed = page.created.clone();
+    }
+    assert!(manifest.save(&manifest_file).is_ok());
+    dirs.write("rayon.md", "---\ntitle: Rayon\n---\n# Rayon\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    let recent = dirs.read("recent.html");
+    let rayon = recent.find("<a href=\"rayon.html\">Rayon</a> changed</li>").unwrap();
+    assert!(rayon < recent.find("serde.html").unwrap());
+    assert!(recent.contains("<h2>2017-05-04</h2>"));
+
+    // Disabled listings are removed with the next build
+    let mut config = Config::default();
+    config.recent.enabled = false;
+    config.sitemap.robots = false;
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    assert!(!dirs.output.join("recent.html").exists());
+    assert!(!dirs.output.join("robots.txt").exists());
+    assert!(dirs.output.join("index.html").exists());
+}
+
+#[test]
+fn test_math() {
+    let dirs = TestDirs::new("math");
+    dirs.write("math.md", "Inline $x^2$ costs $5 and \\$.\n\n\
+                           $$\n\\frac{a}{b}\n$$\n\n\
+                           Broken $\\frac{1$ and `$code$`\n");
+    dirs.write("code.md", "Costs\n\n    $a$\n    $b$\n\n    $c$\n\n\
+                           ````\n$d$\n```\n~~~~\n$e$\n````\n\n~~~\n$f$\n```\n~~~\n\n$g$\n");
+    dirs.write("token.md", "Literal wikimath0z before $y$\n");
+
+    let mut config = Config::default();
+    config.markdown.math = true;
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    assert!(report.pages[0].warnings.is_empty());
+    assert_eq!(report.pages[1].warnings, vec!["Invalid math '\\frac{1': Missing '}'".to_owned()]);
+
+    let content = dirs.read("math.html");
+    assert!(content.contains("Inline <math xmlns=\"http://www.w3.org/1998/Math/MathML\">\
+                              <msup><mi>x</mi><mn>2</mn></msup></math> costs $5 and $."));
+    assert!(content.contains("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" \
+                              display=\"block\"><mfrac><mrow><mi>a</mi></mrow>\
+                              <mrow><mi>b</mi></mrow></mfrac></math>"));
+    assert!(content.contains("<code class=\"math-error\" title=\"Missing &#39;}&#39;\">\
+                              $\\frac{1$</code>"));
+    assert!(content.contains("<code>$code$</code>"));
+
+    // Following indented lines and fences closed only by a matching fence stay code
+    let content = dirs.read("code.html");
+    for code in &["a", "b", "c", "d", "e", "f"] {
+        assert!(content.contains(&format!("${}$", code)));
+    }
+    assert!(content.contains("<mi>g</mi>"));
+
+    // Text looking like a placeholder is kept as it is
+    assert!(dirs.read("token.html").contains("Literal wikimath0z before <math \
+                                               xmlns=\"http://www.w3.org/1998/Math/MathML\">\
+                                               <mi>y</mi></math>"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_diagrams() {
+    let dirs = TestDirs::new("diagram");
+    dirs.write("diagram.md", "wikidiagram0z\n\n```fake\na -> b\n```\n\n```missing\nc -> d\n```\n");
+
+    let mut config = Config::default();
+    config.diagrams.enabled = true;
+    let command = |arguments: &[&str]| arguments.iter().map(|a| a.to_string()).collect();
+    config.diagrams.commands.insert("fake".to_owned(),
+                                    command(&["sh", "-c", "echo '<?xml version=\"1.0\"?>'; \
+                                                           printf '<svg>'; cat; echo '</svg>'"]));
+    config.diagrams.commands.insert("missing".to_owned(), command(&["_not_installed_tool_"]));
+    let mut wiki = Wiki::with_config(config);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    assert_eq!(report.pages[0].warnings,
+               vec!["Unable to render the missing diagram: '_not_installed_tool_' is not \
+                     installed".to_owned()]);
+
+    let content = dirs.read("diagram.html");
+    assert!(content.contains("<figure class=\"diagram\"><svg>a -> b\n</svg></figure>"));
+    assert!(content.contains("<pre class=\"diagram-source\"><code>c -&gt; d\n</code></pre>"));
+    assert!(content.contains("<p>wikidiagram0z</p>\n\n<figure"));
+
+    // Pages with missing diagrams are rendered again, taking the others from the cache
+    let cache = dirs.output.join(".diagrams");
+    let cached: Vec<_> = fs::read_dir(&cache).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(cached.len(), 1);
+    let mut file = fs::File::create(&cached[0]).unwrap();
+    assert!(file.write_all(b"<svg>cached</svg>").is_ok());
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    assert_eq!(report.pages[0].status, PageStatus::Rendered);
+    assert!(dirs.read("diagram.html")
+                .contains("<figure class=\"diagram\"><svg>cached</svg></figure>"));
+
+    // Diagrams no page uses any more are removed from the cache
+    dirs.write("diagram.md", "```fake\nb -> c\n```\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    assert!(!cached[0].exists());
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+
+    // Pages which are not rendered keep their diagrams, also when failing to load
+    dirs.write("other.md", "# Other\n");
+    dirs.write("diagram.md", "---\nweight: heavy\n---\n```fake\nb -> c\n```\n");
+    for _ in 0..2 {
+        assert!(wiki.read_from_directory(&dirs.input).is_ok());
+        assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+    }
+    dirs.write("diagram.md", "```fake\nb -> c\n```\n\n```missing\nc -> d\n```\n");
+    dirs.write("other.md", "---\nweight: heavy\n---\n");
+    wiki.set_fail_fast(true);
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    assert_eq!(report.pages[0].status, PageStatus::Skipped);
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+}
+
+#[test]
+fn test_includes() {
+    let dirs = TestDirs::new("include");
+    dirs.write("snippets/location.md", "---\nhidden: true\n---\n# Location\nSee [map](map.md).\n\n\
+                                        {{#include rules.md#conduct}}\n");
+    dirs.write("snippets/rules.md", "# Rules\n## Conduct\nBe nice.\n## Other\nNope.\n");
+    dirs.write("snippets/map.md", "# Map\n");
+    dirs.write("meetup.md", "# Meetup\n## Where\n{{#include snippets/location.md}}\n\n\
+                             wikiinclude0z\n\n{{#include meetup.md}}\n");
+
+    let mut wiki = Wiki::new();
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    let meetup = report.pages.iter().find(|page| page.source.ends_with("meetup.md")).unwrap();
+    assert_eq!(meetup.warnings.len(), 1);
+    assert!(meetup.warnings[0].starts_with("Include cycle"));
+    let content = dirs.read("meetup.html");
+    assert!(content.contains("<h2 id='where'>Where</h2>\n\n<h3 id='location'>Location</h3>\n\n\
+                              <p>See <a href='snippets/map.html'>map</a>.</p>\n\n\
+                              <h4 id='conduct'>Conduct</h4>\n\n<p>Be nice.</p>"));
+    assert!(!content.contains("Nope."));
+    assert!(content.contains("<p>wikiinclude0z</p>\n\n<p class=\"include-error\">Include cycle "));
+
+    // Changing a nested include renders the page again
+    dirs.write("snippets/rules.md", "# Rules\n## Conduct\nBe very nice.\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    let meetup = report.pages.iter().find(|page| page.source.ends_with("meetup.md")).unwrap();
+    assert_eq!(meetup.status, PageStatus::Rendered);
+    assert!(dirs.read("meetup.html").contains("<p>Be very nice.</p>"));
+
+    // Includes added to a nested include after the first build are dependencies as well
+    dirs.write("snippets/rules.md", "# Rules\n## Conduct\n{{#include extra.md}}\n");
+    dirs.write("snippets/extra.md", "Be kind.\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    assert!(wiki.read_content_from_current_paths(&dirs.input, &dirs.output).is_ok());
+    assert!(dirs.read("meetup.html").contains("<p>Be kind.</p>"));
+    dirs.write("snippets/extra.md", "Be very kind.\n");
+    assert!(wiki.read_from_directory(&dirs.input).is_ok());
+    let report = wiki.read_content_from_current_paths(&dirs.input, &dirs.output).unwrap();
+    let meetup = report.pages.iter().find(|page| page.source.ends_with("meetup.md")).unwrap();
+    assert_eq!(meetup.status, PageStatus::Rendered);
+    assert!(dirs.read("meetup.html").contains("<p>Be very kind.</p>"));
+}
@@ -0,0 +1,27 @@
+extern crate wikilib;
+
+use wikilib::Wiki;
+use wikilib::error::*;
+use wikilib::server::Binding;
+
+use std::net::TcpListener;
+
+#[test]
+fn binding_display() {
+    assert_eq!(Binding::default().to_string(), "localhost:30000");
+    assert_eq!(Binding::tcp("[::1]", 8080).to_string(), "[::1]:8080");
+    assert_eq!(Binding::tcp("0.0.0.0", 80).to_string(), "0.0.0.0:80");
+}
+
+#[test]
+fn serve_on_address_in_use() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut wiki = Wiki::new();
+    match wiki.serve("html", &Binding::tcp("127.0.0.1", port)) {
+        Err(Error(ErrorKind::AddressInUse(address), _)) => {
+            assert_eq!(address, format!("127.0.0.1:{}", port))
+        }
+        _ => panic!("`serve` should fail when the address is in use."),
+    }
+}